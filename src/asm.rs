@@ -0,0 +1,549 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{chip8::PROGRAM_START, instructions::Instruction, registers::Register};
+
+/// Stops files which include themselves from recursing forever
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Every mnemonic, so a known one with wrong operands gets a more helpful error
+const MNEMONICS: [&str; 33] = [
+    "CLS", "RET", "JP", "CALL", "SE", "SNE", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SUBN", "SHR",
+    "SHL", "RND", "DRW", "SKP", "SKNP", "SCD", "SCU", "SCR", "SCL", "EXIT", "LOW", "HIGH", "SAVE",
+    "LOAD", "PLANE", "AUDIO", "PITCH", "DB", "DW",
+];
+
+/// SUPER-CHIP and XO-CHIP mnemonics
+const EXTENDED: [&str; 12] = [
+    "SCD", "SCU", "SCR", "SCL", "EXIT", "LOW", "HIGH", "SAVE", "LOAD", "PLANE", "AUDIO", "PITCH",
+];
+
+#[derive(Debug)]
+pub struct AsmError {
+    pub file: PathBuf,
+    /// Starts at 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+/// Assembles Cowgod-style source, as printed by the disassembler, into a program loaded at
+/// [`PROGRAM_START`]
+///
+/// Besides instructions, lines can hold `label:` definitions, `NAME EQU value` constants,
+/// `DB`/`DW` data and `INCLUDE "file"` directives. Values are numbers (`0x`, `0b`, `#` or
+/// decimal) or symbols, added or subtracted together, and `;` starts a comment.
+/// Includes are relative to the current directory.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut assembler = Assembler::default();
+    assembler.load(source, Path::new("<source>"), Path::new(""), 0)?;
+    assembler.finish()
+}
+
+/// Same as [`assemble`], with includes relative to the file
+pub fn assemble_file(path: &Path) -> Result<Vec<u8>, AsmError> {
+    let source = std::fs::read_to_string(path).map_err(|err| AsmError {
+        file: path.to_path_buf(),
+        line: 0,
+        message: err.to_string(),
+    })?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut assembler = Assembler::default();
+    assembler.load(&source, path, dir, 0)?;
+    assembler.finish()
+}
+
+#[derive(Default)]
+struct Assembler {
+    /// Labels and constants
+    symbols: HashMap<String, i64>,
+    statements: Vec<Statement>,
+    /// Every file loaded, indexed by [`Statement::file`]
+    files: Vec<PathBuf>,
+    /// Number of bytes assembled so far
+    size: usize,
+}
+
+/// A line which emits bytes, kept until every label is known
+struct Statement {
+    file: usize,
+    line: usize,
+    mnemonic: String,
+    operands: Vec<String>,
+}
+
+impl Assembler {
+    /// First pass, which finds the address of every label
+    fn load(
+        &mut self,
+        source: &str,
+        file: &Path,
+        dir: &Path,
+        depth: usize,
+    ) -> Result<(), AsmError> {
+        let file_idx = self.files.len();
+        self.files.push(file.to_path_buf());
+
+        for (idx, text) in source.lines().enumerate() {
+            let error = |message: String| AsmError {
+                file: file.to_path_buf(),
+                line: idx + 1,
+                message,
+            };
+
+            let text = text.split(';').next().unwrap_or_default();
+            let text = self.define_labels(text).map_err(error)?;
+            if text.is_empty() {
+                continue;
+            }
+
+            let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+            let rest = rest.trim();
+
+            if let Some((kw, value)) = rest.split_once(char::is_whitespace) {
+                if kw.eq_ignore_ascii_case("EQU") {
+                    let value = Eval::strict(&self.symbols).value(value).map_err(error)?;
+                    self.define(mnemonic, value).map_err(error)?;
+                    continue;
+                }
+            }
+
+            if mnemonic.eq_ignore_ascii_case("INCLUDE") {
+                if depth == MAX_INCLUDE_DEPTH {
+                    return Err(error("includes are nested too deeply".into()));
+                }
+                let path = dir.join(rest.trim_matches('"'));
+                let source = std::fs::read_to_string(&path)
+                    .map_err(|err| error(format!("failed to include {}: {err}", path.display())))?;
+                let include_dir = path.parent().unwrap_or(dir).to_path_buf();
+                self.load(&source, &path, &include_dir, depth + 1)?;
+                continue;
+            }
+
+            let statement = Statement {
+                file: file_idx,
+                line: idx + 1,
+                mnemonic: mnemonic.to_ascii_uppercase(),
+                operands: if rest.is_empty() {
+                    Vec::new()
+                } else {
+                    rest.split(',').map(|x| x.trim().to_string()).collect()
+                },
+            };
+            // Labels defined later aren't known yet, but can't change the size, so values are only
+            // checked in the second pass
+            self.size += statement
+                .encode(&Eval::lenient(&self.symbols))
+                .map_err(error)?
+                .len();
+            self.statements.push(statement);
+        }
+
+        Ok(())
+    }
+
+    /// Defines any labels at the start of `text`, returning the rest of it
+    fn define_labels<'a>(&mut self, mut text: &'a str) -> Result<&'a str, String> {
+        loop {
+            text = text.trim();
+            match text.split_once(':') {
+                Some((label, rest)) if is_symbol(label) => {
+                    let addr = usize::from(PROGRAM_START) + self.size;
+                    self.define(label, i64::try_from(addr).unwrap_or(i64::MAX))?;
+                    text = rest;
+                }
+                _ => return Ok(text),
+            }
+        }
+    }
+
+    fn define(&mut self, name: &str, value: i64) -> Result<(), String> {
+        if !is_symbol(name) {
+            return Err(format!("invalid symbol name '{name}'"));
+        }
+        if self.symbols.insert(name.to_string(), value).is_some() {
+            return Err(format!("'{name}' is already defined"));
+        }
+        Ok(())
+    }
+
+    /// Second pass, which encodes every statement now that all labels are known
+    fn finish(self) -> Result<Vec<u8>, AsmError> {
+        let eval = Eval::strict(&self.symbols);
+        let mut prg = Vec::with_capacity(self.size);
+
+        for statement in &self.statements {
+            let bytes = statement.encode(&eval).map_err(|message| AsmError {
+                file: self.files[statement.file].clone(),
+                line: statement.line,
+                message,
+            })?;
+            prg.extend(bytes);
+        }
+
+        Ok(prg)
+    }
+}
+
+impl Statement {
+    fn encode(&self, eval: &Eval<'_>) -> Result<Vec<u8>, String> {
+        match self.mnemonic.as_str() {
+            "DB" => self.operands.iter().map(|x| eval.byte(x)).collect(),
+            "DW" => Ok(self
+                .operands
+                .iter()
+                .map(|x| eval.long(x))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .flat_map(u16::to_be_bytes)
+                .collect()),
+            _ => {
+                let operands: Vec<Operand<'_>> = self.operands.iter().map(|x| operand(x)).collect();
+                Ok(instruction(&self.mnemonic, &operands, eval)?.encode())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Operand<'a> {
+    Reg(Register),
+    I,
+    /// `[I]`, the memory pointed to by the index register
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    B,
+    Hf,
+    R,
+    Long(&'a str),
+    Value(&'a str),
+}
+
+fn operand(s: &str) -> Operand<'_> {
+    match s.to_ascii_uppercase().as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "B" => Operand::B,
+        "HF" => Operand::Hf,
+        "R" => Operand::R,
+        upper => match register(upper) {
+            Some(reg) => Operand::Reg(reg),
+            None if upper.starts_with("LONG ") => Operand::Long(s[5..].trim()),
+            None => Operand::Value(s),
+        },
+    }
+}
+
+fn register(s: &str) -> Option<Register> {
+    let digit = s.strip_prefix('V')?;
+    if digit.len() != 1 {
+        return None;
+    }
+    Register::from_u16(u16::from_str_radix(digit, 16).ok()?)
+}
+
+fn instruction(
+    mnemonic: &str,
+    operands: &[Operand<'_>],
+    eval: &Eval<'_>,
+) -> Result<Instruction, String> {
+    use Instruction as I;
+    use Operand::{Reg, Value};
+
+    Ok(match (mnemonic, operands) {
+        ("LD", _) => load(operands, eval)?,
+        _ if EXTENDED.contains(&mnemonic) => extended(mnemonic, operands, eval)?,
+        ("CLS", []) => I::ClearDisplay,
+        ("RET", []) => I::ReturnSubroutine,
+        ("JP", [Value(addr)]) => I::Jump {
+            addr: eval.addr(addr)?,
+        },
+        ("JP", [Reg(Register::V0), Value(addr)]) => I::JumpOffset {
+            addr: eval.addr(addr)?,
+        },
+        ("CALL", [Value(addr)]) => I::CallSubroutine {
+            addr: eval.addr(addr)?,
+        },
+        ("SE", [Reg(reg), Value(num)]) => I::SkipEq {
+            reg: *reg,
+            num: eval.byte(num)?,
+        },
+        ("SNE", [Reg(reg), Value(num)]) => I::SkipNe {
+            reg: *reg,
+            num: eval.byte(num)?,
+        },
+        ("SE", [Reg(reg1), Reg(reg2)]) => I::SkipEqReg {
+            reg1: *reg1,
+            reg2: *reg2,
+        },
+        ("SNE", [Reg(reg1), Reg(reg2)]) => I::SkipNeReg {
+            reg1: *reg1,
+            reg2: *reg2,
+        },
+        ("ADD", [Reg(reg), Value(val)]) => I::Add {
+            reg: *reg,
+            val: eval.byte(val)?,
+        },
+        ("ADD", [Operand::I, Reg(inreg)]) => I::AddToIndex { inreg: *inreg },
+        (_, [Reg(reg1), Reg(reg2)]) => logic(mnemonic, *reg1, *reg2)?,
+        // Shifting a register in place, for platforms which ignore VY anyway
+        ("SHR" | "SHL", [Reg(reg)]) => logic(mnemonic, *reg, *reg)?,
+        ("RND", [Reg(outreg), Value(val)]) => I::Rand {
+            outreg: *outreg,
+            val: eval.byte(val)?,
+        },
+        ("DRW", [Reg(xreg), Reg(yreg), Value(height)]) => I::Display {
+            xreg: *xreg,
+            yreg: *yreg,
+            height: eval.nibble(height)?,
+        },
+        ("SKP", [Reg(keyreg)]) => I::SkipIfKey { keyreg: *keyreg },
+        ("SKNP", [Reg(keyreg)]) => I::SkipIfNotKey { keyreg: *keyreg },
+        _ if MNEMONICS.contains(&mnemonic) => {
+            return Err(format!("invalid operands for {mnemonic}"));
+        }
+        _ => return Err(format!("unknown instruction '{mnemonic}'")),
+    })
+}
+
+/// Instructions between two registers, in the 0x8 category
+fn logic(mnemonic: &str, reg1: Register, reg2: Register) -> Result<Instruction, String> {
+    use Instruction as I;
+
+    Ok(match mnemonic {
+        "OR" => I::Or { reg1, reg2 },
+        "AND" => I::And { reg1, reg2 },
+        "XOR" => I::Xor { reg1, reg2 },
+        "ADD" => I::AddReg { reg1, reg2 },
+        "SUB" => I::Sub1 { reg1, reg2 },
+        "SUBN" => I::Sub2 { reg1, reg2 },
+        "SHR" => I::Shr { reg1, reg2 },
+        "SHL" => I::Shl { reg1, reg2 },
+        _ if MNEMONICS.contains(&mnemonic) => {
+            return Err(format!("invalid operands for {mnemonic}"));
+        }
+        _ => return Err(format!("unknown instruction '{mnemonic}'")),
+    })
+}
+
+/// The many forms of `LD`
+fn load(operands: &[Operand<'_>], eval: &Eval<'_>) -> Result<Instruction, String> {
+    use Instruction as I;
+    use Operand::{Dt, Hf, IndirectI, Long, Reg, St, Value, B, F, K, R};
+
+    Ok(match operands {
+        [Reg(reg), Value(val)] => I::Set {
+            reg: *reg,
+            val: eval.byte(val)?,
+        },
+        [Reg(reg1), Reg(reg2)] => I::SetReg {
+            reg1: *reg1,
+            reg2: *reg2,
+        },
+        [Operand::I, Value(val)] => I::SetIndex {
+            val: eval.addr(val)?,
+        },
+        [Operand::I, Long(val)] => I::LongSetIndex {
+            val: eval.long(val)?,
+        },
+        [Reg(outreg), Dt] => I::GetDelayTimer { outreg: *outreg },
+        [Dt, Reg(inreg)] => I::SetDelayTimer { inreg: *inreg },
+        [St, Reg(inreg)] => I::SetSoundTimer { inreg: *inreg },
+        [Reg(keyreg), K] => I::WaitForKey { keyreg: *keyreg },
+        [F, Reg(inreg)] => I::GetFontChar { inreg: *inreg },
+        [B, Reg(inreg)] => I::BinToDec { inreg: *inreg },
+        [IndirectI, Reg(inreg_max)] => I::StoreMem {
+            inreg_max: *inreg_max,
+        },
+        [Reg(outreg_max), IndirectI] => I::LoadMem {
+            outreg_max: *outreg_max,
+        },
+        [Hf, Reg(inreg)] => I::GetBigFontChar { inreg: *inreg },
+        [R, Reg(inreg_max)] => I::StoreFlags {
+            inreg_max: *inreg_max,
+        },
+        [Reg(outreg_max), R] => I::LoadFlags {
+            outreg_max: *outreg_max,
+        },
+        _ => return Err("invalid operands for LD".into()),
+    })
+}
+
+/// SUPER-CHIP and XO-CHIP instructions
+fn extended(
+    mnemonic: &str,
+    operands: &[Operand<'_>],
+    eval: &Eval<'_>,
+) -> Result<Instruction, String> {
+    use Instruction as I;
+    use Operand::{Reg, Value};
+
+    Ok(match (mnemonic, operands) {
+        ("SCD", [Value(amount)]) => I::ScrollDown {
+            amount: eval.nibble(amount)?,
+        },
+        ("SCR", []) => I::ScrollRight,
+        ("SCL", []) => I::ScrollLeft,
+        ("EXIT", []) => I::Exit,
+        ("LOW", []) => I::LowRes,
+        ("HIGH", []) => I::HighRes,
+        ("SCU", [Value(amount)]) => I::ScrollUp {
+            amount: eval.nibble(amount)?,
+        },
+        ("SAVE", [Reg(reg1), Reg(reg2)]) => I::StoreRange {
+            reg1: *reg1,
+            reg2: *reg2,
+        },
+        ("LOAD", [Reg(reg1), Reg(reg2)]) => I::LoadRange {
+            reg1: *reg1,
+            reg2: *reg2,
+        },
+        ("PLANE", [Value(planes)]) => I::SelectPlanes {
+            planes: eval.nibble(planes)?,
+        },
+        ("AUDIO", []) => I::LoadAudioPattern,
+        ("PITCH", [Reg(inreg)]) => I::SetPitch { inreg: *inreg },
+        _ => return Err(format!("invalid operands for {mnemonic}")),
+    })
+}
+
+/// Evaluates operand values
+struct Eval<'a> {
+    symbols: &'a HashMap<String, i64>,
+    /// Whether undefined symbols are an error, or evaluate to 0 without checking that values fit,
+    /// as the first pass only needs statement sizes
+    strict: bool,
+}
+
+impl<'a> Eval<'a> {
+    const fn strict(symbols: &'a HashMap<String, i64>) -> Self {
+        Self {
+            symbols,
+            strict: true,
+        }
+    }
+
+    const fn lenient(symbols: &'a HashMap<String, i64>) -> Self {
+        Self {
+            symbols,
+            strict: false,
+        }
+    }
+
+    fn value(&self, expr: &str) -> Result<i64, String> {
+        let expr = expr.trim();
+        // A leading minus negates the first term
+        let (mut sign, mut rest) = expr.strip_prefix('-').map_or((1, expr), |rest| (-1, rest));
+        let mut total = 0_i64;
+
+        loop {
+            let end = rest.find(['+', '-']).unwrap_or(rest.len());
+            let term = rest[..end].trim();
+            if term.is_empty() {
+                return Err(format!("invalid value '{expr}'"));
+            }
+            total = self
+                .term(term)?
+                .checked_mul(sign)
+                .and_then(|term| total.checked_add(term))
+                .ok_or("value out of range")?;
+
+            match rest[end..].chars().next() {
+                Some(op) => {
+                    sign = if op == '-' { -1 } else { 1 };
+                    rest = &rest[end + 1..];
+                }
+                None => return Ok(total),
+            }
+        }
+    }
+
+    fn term(&self, term: &str) -> Result<i64, String> {
+        let number = if let Some(hex) = term.strip_prefix("0x").or_else(|| term.strip_prefix('#')) {
+            i64::from_str_radix(hex, 16)
+        } else if let Some(bin) = term.strip_prefix("0b") {
+            i64::from_str_radix(bin, 2)
+        } else if term.starts_with(|c: char| c.is_ascii_digit()) {
+            term.parse()
+        } else {
+            return match self.symbols.get(term) {
+                Some(&value) => Ok(value),
+                None if self.strict => Err(format!("undefined symbol '{term}'")),
+                None => Ok(0),
+            };
+        };
+
+        number.map_err(|_| format!("invalid number '{term}'"))
+    }
+
+    fn ranged<T: TryFrom<i64> + Default>(
+        &self,
+        expr: &str,
+        max: i64,
+        what: &str,
+    ) -> Result<T, String> {
+        let value = self.value(expr)?;
+        if !self.strict {
+            return Ok(T::default());
+        }
+        Some(value)
+            .filter(|value| (0..=max).contains(value))
+            .and_then(|value| T::try_from(value).ok())
+            .ok_or_else(|| out_of_range(expr, value, what))
+    }
+
+    /// Negative values are stored as two's complement, such as for `ADD V0, -1`
+    fn byte(&self, expr: &str) -> Result<u8, String> {
+        let value = self.value(expr)?;
+        if !self.strict {
+            return Ok(0);
+        }
+        u8::try_from(value)
+            .or_else(|_| i8::try_from(value).map(i8::cast_unsigned))
+            .map_err(|_| out_of_range(expr, value, "a byte"))
+    }
+
+    fn nibble(&self, expr: &str) -> Result<u8, String> {
+        self.ranged(expr, 0xF, "a nibble")
+    }
+
+    fn addr(&self, expr: &str) -> Result<u16, String> {
+        self.ranged(expr, 0xFFF, "12 bits")
+    }
+
+    fn long(&self, expr: &str) -> Result<u16, String> {
+        self.ranged(expr, 0xFFFF, "16 bits")
+    }
+}
+
+fn out_of_range(expr: &str, value: i64, what: &str) -> String {
+    if expr.parse() == Ok(value) {
+        format!("{value} doesn't fit in {what}")
+    } else {
+        format!("{expr} = {value} doesn't fit in {what}")
+    }
+}
+
+fn is_symbol(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
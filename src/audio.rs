@@ -0,0 +1,136 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+/// Samples per second produced for every sink
+pub const SAMPLE_RATE: u32 = 44100;
+/// Samples making up one 60 Hz frame
+pub const SAMPLES_PER_FRAME: usize = SAMPLE_RATE as usize / 60;
+
+/// Destination for generated audio, such as a sound card or a file
+pub trait AudioSink {
+    /// Receives mono samples in the range `-1.0..=1.0`, at [`SAMPLE_RATE`]
+    fn write(&mut self, samples: &[f32]);
+}
+
+/// Sends the same audio to every sink, such as a sound card and a recording
+impl AudioSink for Vec<Box<dyn AudioSink>> {
+    fn write(&mut self, samples: &[f32]) {
+        for sink in self {
+            sink.write(samples);
+        }
+    }
+}
+
+/// What the machine wants played during a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    Silent,
+    Beep,
+    /// XO-CHIP 1-bit pattern, played from the most significant bit of the first byte
+    Pattern {
+        pattern: [u8; 16],
+        pitch: u8,
+    },
+}
+
+impl Sound {
+    /// XO-CHIP playback rate in bits per second for a pitch register value
+    pub fn pattern_rate(pitch: u8) -> f32 {
+        4000.0 * ((f32::from(pitch) - 64.0) / 48.0).exp2()
+    }
+}
+
+/// Generator for the sound timer: a square wave, or an XO-CHIP pattern when one is loaded
+#[derive(Debug, Clone)]
+pub struct Beeper {
+    /// Square wave frequency in Hz
+    pub pitch: f32,
+    /// Amplitude from 0.0 to 1.0
+    pub volume: f32,
+    /// Position within the current wave period or pattern, from 0.0 to 1.0
+    phase: f32,
+    samples: Vec<f32>,
+}
+
+impl Beeper {
+    pub fn new(pitch: f32, volume: f32) -> Self {
+        Self {
+            pitch,
+            volume,
+            phase: 0.0,
+            samples: vec![0.0; SAMPLES_PER_FRAME],
+        }
+    }
+
+    /// Generates one 60 Hz frame of audio into the sink
+    pub fn render_frame(&mut self, sound: Sound, sink: &mut dyn AudioSink) {
+        #[allow(clippy::cast_precision_loss)]
+        let sample_rate = SAMPLE_RATE as f32;
+
+        match sound {
+            Sound::Silent => {
+                // Restart the wave, so every beep sounds the same
+                self.phase = 0.0;
+                self.samples.fill(0.0);
+            }
+            Sound::Beep => {
+                let step = self.pitch / sample_rate;
+                for sample in &mut self.samples {
+                    let high = self.phase < 0.5;
+                    *sample = if high { self.volume } else { -self.volume };
+                    self.phase = (self.phase + step).fract();
+                }
+            }
+            Sound::Pattern { pattern, pitch } => {
+                // The whole 128 bit pattern makes up one period
+                let step = Sound::pattern_rate(pitch) / 128.0 / sample_rate;
+                for sample in &mut self.samples {
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    let bit = (self.phase * 128.0) as usize % 128;
+                    let high = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+                    *sample = if high { self.volume } else { -self.volume };
+                    self.phase = (self.phase + step).fract();
+                }
+            }
+        }
+
+        sink.write(&self.samples);
+    }
+}
+
+/// Records audio to a 16-bit mono WAV file
+pub struct WavSink {
+    writer: hound::WavWriter<BufWriter<File>>,
+}
+
+impl WavSink {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, hound::Error> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        Ok(Self {
+            writer: hound::WavWriter::create(path, spec)?,
+        })
+    }
+
+    /// Finishes writing the WAV header, which also happens on drop, but with errors ignored
+    pub fn finish(self) -> Result<(), hound::Error> {
+        self.writer.finalize()
+    }
+}
+
+impl AudioSink for WavSink {
+    fn write(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            #[allow(clippy::cast_possible_truncation)]
+            let sample = (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16;
+            if let Err(err) = self.writer.write_sample(sample) {
+                log::error!("Failed to write audio sample: {err}");
+                return;
+            }
+        }
+    }
+}
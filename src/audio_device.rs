@@ -0,0 +1,61 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use chip8::audio::{AudioSink, SAMPLE_RATE};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+// Enough buffered audio to ride out scheduling hiccups, without noticeable latency
+const MAX_BUFFERED: usize = SAMPLE_RATE as usize / 10;
+
+/// Plays audio on the default output device
+pub struct DeviceSink {
+    buffer: Arc<Mutex<VecDeque<f32>>>,
+    // Playback stops when the stream is dropped
+    _stream: cpal::Stream,
+}
+
+impl DeviceSink {
+    pub fn open() -> Result<Self, Box<dyn std::error::Error>> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device")?;
+        let channels = device.default_output_config()?.channels();
+        let config = cpal::StreamConfig {
+            channels,
+            sample_rate: cpal::SampleRate(SAMPLE_RATE),
+            buffer_size: cpal::BufferSize::Default,
+        };
+
+        let buffer = Arc::new(Mutex::new(VecDeque::new()));
+        let shared = Arc::clone(&buffer);
+        let stream = device.build_output_stream(
+            &config,
+            move |data: &mut [f32], _| {
+                let mut buffer = shared.lock().unwrap_or_else(PoisonError::into_inner);
+                // Same sample on every channel, silence if we've fallen behind
+                for frame in data.chunks_mut(channels.into()) {
+                    frame.fill(buffer.pop_front().unwrap_or(0.0));
+                }
+            },
+            |err| log::error!("Audio stream error: {err}"),
+            None,
+        )?;
+        stream.play()?;
+
+        Ok(Self {
+            buffer,
+            _stream: stream,
+        })
+    }
+}
+
+impl AudioSink for DeviceSink {
+    fn write(&mut self, samples: &[f32]) {
+        let mut buffer = self.buffer.lock().unwrap_or_else(PoisonError::into_inner);
+        buffer.extend(samples);
+        let excess = buffer.len().saturating_sub(MAX_BUFFERED);
+        buffer.drain(..excess);
+    }
+}
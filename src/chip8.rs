@@ -1,4 +1,4 @@
-use crate::{registers::Registers, stack::Stack};
+use crate::{fault::Fault, platform::Platform, registers::Registers, stack::Stack};
 
 const FONT: [u8; 16 * 5] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -19,36 +19,129 @@ const FONT: [u8; 16 * 5] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+// SUPER-CHIP 8x10 font, with A-F as extended by XO-CHIP
+const BIG_FONT: [u8; 16 * 10] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// Due to convention, programs are loaded at 512 bytes
+pub const PROGRAM_START: u16 = 0x200;
+
+/// Whether an instruction read or wrote memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A range of memory touched while executing an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemAccess {
+    pub kind: AccessKind,
+    pub addr: u16,
+    pub len: u16,
+}
+
+impl MemAccess {
+    /// Whether any of the accessed bytes are within `start..=end`
+    pub const fn overlaps(&self, start: u16, end: u16) -> bool {
+        let last = self.addr.saturating_add(self.len.saturating_sub(1));
+        self.len > 0 && self.addr <= end && last >= start
+    }
+}
+
 #[derive(Debug)]
 pub struct Chip8 {
-    pub mem: [u8; 4096],
+    pub mem: Vec<u8>,
     pub stack: Stack,
     pub registers: Registers,
     pub pc: u16,
     pub delay_timer: u8,
     pub sound_timer: u8,
+    /// SUPER-CHIP RPL user flags, saved and loaded by FX75/FX85
+    pub flags: [u8; 16],
+    /// XO-CHIP 1-bit audio samples loaded by F002, played instead of the beeper once set
+    pub audio_pattern: Option<[u8; 16]>,
+    /// XO-CHIP playback rate set by FX3A, where 64 is 4000 samples per second
+    pub audio_pitch: u8,
+    /// Memory accessed by the last executed instruction, including its fetch, for watchpoints
+    pub accesses: Vec<MemAccess>,
+    /// Source of CXNN random numbers, saved in states so runs can be reproduced
+    pub rng: fastrand::Rng,
 }
 
 impl Chip8 {
-    pub fn load_prg(prg: &[u8]) -> Self {
-        let mut mem = [0; 4096];
+    pub fn load_prg(prg: &[u8], platform: Platform) -> Result<Self, Fault> {
+        let mut mem = vec![0; platform.memory_size()];
         // Due to convention, font starts at 0x50
         mem[0x50..0xA0].copy_from_slice(FONT.as_slice());
-        // Due to convention, program starts at 512 bytes
-        let prg_start = prg.len() + 512;
-        mem[512..prg_start].copy_from_slice(prg);
+        // Big font directly follows it
+        mem[0xA0..0x140].copy_from_slice(BIG_FONT.as_slice());
+        let prg_start = usize::from(PROGRAM_START);
+        let prg_end = prg.len() + prg_start;
+        mem.get_mut(prg_start..prg_end)
+            .ok_or(Fault::RomTooLarge { size: prg.len() })?
+            .copy_from_slice(prg);
 
         let stack = Stack::new();
 
         let registers = Registers::new();
 
-        Self {
+        Ok(Self {
             mem,
             stack,
             registers,
-            pc: 512,
+            pc: PROGRAM_START,
             delay_timer: 0,
             sound_timer: 0,
-        }
+            flags: [0; 16],
+            audio_pattern: None,
+            audio_pitch: 64,
+            accesses: Vec::new(),
+            rng: fastrand::Rng::new(),
+        })
+    }
+
+    /// Borrows `len` bytes of memory starting at `addr`, faulting if any of them are out of bounds
+    pub fn mem_range(&self, addr: usize, len: usize) -> Result<&[u8], Fault> {
+        addr.checked_add(len)
+            .and_then(|end| self.mem.get(addr..end))
+            .ok_or_else(|| Fault::MemoryOutOfBounds {
+                addr: addr.max(self.mem.len()),
+            })
+    }
+
+    pub fn mem_range_mut(&mut self, addr: usize, len: usize) -> Result<&mut [u8], Fault> {
+        let mem_len = self.mem.len();
+        addr.checked_add(len)
+            .and_then(|end| self.mem.get_mut(addr..end))
+            .ok_or_else(|| Fault::MemoryOutOfBounds {
+                addr: addr.max(mem_len),
+            })
+    }
+
+    /// Logs an access to memory that was already checked to be in bounds
+    pub(crate) fn record_access(&mut self, kind: AccessKind, addr: usize, len: usize) {
+        // Unwraps are ok, memory is at most 64 KiB
+        self.accesses.push(MemAccess {
+            kind,
+            addr: u16::try_from(addr).unwrap(),
+            len: u16::try_from(len).unwrap(),
+        });
     }
 }
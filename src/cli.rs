@@ -1,28 +1,242 @@
-use std::{convert::Infallible, path::PathBuf};
+use std::{convert::Infallible, ffi::OsString, path::PathBuf, str::FromStr};
+
+use chip8::{instructions::Syntax, platform::Platform, quirks::Quirks};
+
+pub enum Command {
+    Run(Box<Args>),
+    Disasm(Disasm),
+    Asm(Asm),
+}
 
 pub struct Args {
     pub program: PathBuf,
-    pub colors: Colors,
+    // Settings left unset here are detected from the ROM database, or fall back to defaults
+    pub foreground: Option<u32>,
+    pub background: Option<u32>,
+    pub plane2: Option<u32>,
+    pub blend: Option<u32>,
+    pub platform: Option<Platform>,
+    pub quirks: Option<Quirks>,
+    pub tickrate: Option<usize>,
+    /// Seeds the CXNN random number generator, random if unset
+    pub seed: Option<u64>,
+    /// Replaces the bundled ROM database, such as with a full copy of `programs.json`
+    pub database: Option<PathBuf>,
+    /// Host key layout, replacing the ROM's keymap profile and the default
+    pub keymap: Option<PathBuf>,
+    pub audio: Audio,
+    pub rewind: Rewind,
+    /// Save state to restore before running
+    pub load_state: Option<PathBuf>,
+    /// Records input to this movie file
+    pub record: Option<PathBuf>,
+    /// Plays input back from this movie file, starting from its state
+    pub play: Option<PathBuf>,
+    pub headless: Option<Headless>,
+    /// Start paused with a debugger reading commands from stdin
+    pub debug: bool,
+    /// Start paused, waiting for GDB to connect on this localhost port
+    pub gdb: Option<u16>,
+}
+
+/// Arguments to the `disasm` subcommand
+pub struct Disasm {
+    pub program: PathBuf,
+    pub syntax: Syntax,
+    /// Instructions the platform doesn't support are shown as data
+    pub platform: Platform,
+}
+
+/// Arguments to the `asm` subcommand
+pub struct Asm {
+    pub source: PathBuf,
+    /// Defaults to the source path with a `.ch8` extension
+    pub output: PathBuf,
+}
+
+pub struct Audio {
+    /// Beeper frequency in Hz
+    pub pitch: f32,
+    /// Beeper amplitude from 0.0 to 1.0
+    pub volume: f32,
+    /// Records all audio to this file
+    pub wav: Option<PathBuf>,
+    /// Don't play audio on the sound card
+    #[cfg_attr(not(feature = "cpal"), allow(dead_code))]
+    pub mute: bool,
+}
+
+#[derive(Clone, Copy)]
+pub struct Rewind {
+    /// How far back rewinding can go, 0 to disable it
+    pub seconds: u32,
+    /// Memory budget for rewind states in MiB, dropping the oldest once exceeded
+    pub memory: usize,
+}
+
+impl Rewind {
+    /// A state per frame
+    pub fn buffer(self) -> chip8::rewind::Rewind {
+        chip8::rewind::Rewind::new(
+            usize::try_from(self.seconds)
+                .unwrap_or(usize::MAX)
+                .saturating_mul(60),
+            self.memory.saturating_mul(1024 * 1024),
+        )
+    }
 }
 
 pub struct Colors {
     pub foreground: u32,
     pub background: u32,
+    /// Pixels only set in the second XO-CHIP plane
+    pub plane2: u32,
+    /// Pixels set in both XO-CHIP planes
+    pub blend: u32,
+}
+
+pub struct Headless {
+    /// Maximum number of 60 Hz frames to run for, defaulting to 600 or the length of the movie
+    /// being played
+    pub frames: Option<u32>,
+    /// Key states to apply, sorted by frame
+    pub keys: Vec<KeyEvent>,
+    pub dump: DumpFormat,
+}
+
+/// Keys held down starting from a given frame, until the next event
+pub struct KeyEvent {
+    pub frame: u32,
+    pub keys: [bool; 16],
+}
+
+#[derive(Clone, Copy)]
+pub enum DumpFormat {
+    Ascii,
+    Hash,
+}
+
+impl FromStr for DumpFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(Self::Ascii),
+            "hash" => Ok(Self::Hash),
+            _ => Err("dump format must be 'ascii' or 'hash'"),
+        }
+    }
 }
 
-pub fn parse_args() -> Result<Args, pico_args::Error> {
-    let mut pargs = pico_args::Arguments::from_env();
+pub fn parse_args() -> Result<Command, pico_args::Error> {
+    let mut args: Vec<OsString> = std::env::args_os().skip(1).collect();
+
+    if args.first().is_some_and(|arg| arg == "disasm") {
+        args.remove(0);
+        return parse_disasm(pico_args::Arguments::from_vec(args)).map(Command::Disasm);
+    }
+    if args.first().is_some_and(|arg| arg == "asm") {
+        args.remove(0);
+        return parse_asm(pico_args::Arguments::from_vec(args)).map(Command::Asm);
+    }
+
+    parse_run(pico_args::Arguments::from_vec(args)).map(|args| Command::Run(Box::new(args)))
+}
+
+fn parse_disasm(mut pargs: pico_args::Arguments) -> Result<Disasm, pico_args::Error> {
+    let syntax = pargs.opt_value_from_str("--syntax")?.unwrap_or_default();
+    // Decode every known instruction unless told otherwise
+    let platform = pargs
+        .opt_value_from_str("--platform")?
+        .unwrap_or(Platform::XoChip);
+
+    Ok(Disasm {
+        program: pargs.free_from_fn::<PathBuf, Infallible>(|x| Ok(x.into()))?,
+        syntax,
+        platform,
+    })
+}
+
+fn parse_asm(mut pargs: pico_args::Arguments) -> Result<Asm, pico_args::Error> {
+    let output =
+        pargs.opt_value_from_os_str::<_, PathBuf, Infallible>("--output", |x| Ok(x.into()))?;
+    let source = pargs.free_from_fn::<PathBuf, Infallible>(|x| Ok(x.into()))?;
+
+    Ok(Asm {
+        output: output.unwrap_or_else(|| source.with_extension("ch8")),
+        source,
+    })
+}
+
+fn parse_run(mut pargs: pico_args::Arguments) -> Result<Args, pico_args::Error> {
+    let foreground = pargs.opt_value_from_fn("--foreground", parse_color)?;
+    let background = pargs.opt_value_from_fn("--background", parse_color)?;
+    let plane2 = pargs.opt_value_from_fn("--plane2-color", parse_color)?;
+    let blend = pargs.opt_value_from_fn("--blend-color", parse_color)?;
+    let platform = pargs.opt_value_from_str("--platform")?;
+    let quirks = pargs.opt_value_from_str("--quirks")?;
+    let tickrate = pargs.opt_value_from_str("--tickrate")?;
+    let seed = pargs.opt_value_from_str("--seed")?;
+    let database =
+        pargs.opt_value_from_os_str::<_, _, Infallible>("--database", |x| Ok(x.into()))?;
+    let keymap = pargs.opt_value_from_os_str::<_, _, Infallible>("--keymap", |x| Ok(x.into()))?;
+
+    let audio = Audio {
+        pitch: pargs.opt_value_from_str("--pitch")?.unwrap_or(440.0),
+        volume: pargs
+            .opt_value_from_fn("--volume", parse_volume)?
+            .unwrap_or(0.25),
+        wav: pargs.opt_value_from_os_str::<_, _, Infallible>("--wav", |x| Ok(x.into()))?,
+        mute: pargs.contains("--mute"),
+    };
+
+    let rewind = Rewind {
+        seconds: pargs.opt_value_from_str("--rewind-seconds")?.unwrap_or(10),
+        memory: pargs.opt_value_from_str("--rewind-memory")?.unwrap_or(32),
+    };
+
+    let load_state =
+        pargs.opt_value_from_os_str::<_, _, Infallible>("--load-state", |x| Ok(x.into()))?;
+    let record = pargs.opt_value_from_os_str::<_, _, Infallible>("--record", |x| Ok(x.into()))?;
+    let play = pargs.opt_value_from_os_str::<_, _, Infallible>("--play", |x| Ok(x.into()))?;
+    let debug = pargs.contains("--debug");
+    let gdb = pargs.opt_value_from_str("--gdb")?;
+
+    let headless = if pargs.contains("--headless") {
+        Some(Headless {
+            frames: pargs.opt_value_from_str("--frames")?,
+            keys: pargs
+                .opt_value_from_fn("--keys", parse_key_script)?
+                .unwrap_or_default(),
+            dump: pargs
+                .opt_value_from_str("--dump")?
+                .unwrap_or(DumpFormat::Ascii),
+        })
+    } else {
+        None
+    };
 
     let args = Args {
+        // Parsed last, as it takes the first remaining argument
         program: pargs.free_from_fn::<PathBuf, Infallible>(|x| Ok(x.into()))?,
-        colors: Colors {
-            foreground: pargs
-                .opt_value_from_fn("--foreground", parse_color)?
-                .unwrap_or(0xFF_FF_FF),
-            background: pargs
-                .opt_value_from_fn("--background", parse_color)?
-                .unwrap_or(0x00_00_00),
-        },
+        foreground,
+        background,
+        plane2,
+        blend,
+        platform,
+        quirks,
+        tickrate,
+        seed,
+        database,
+        keymap,
+        audio,
+        rewind,
+        load_state,
+        record,
+        play,
+        headless,
+        debug,
+        gdb,
     };
 
     Ok(args)
@@ -32,3 +246,36 @@ fn parse_color(s: &str) -> Result<u32, &'static str> {
     let s = s.trim_start_matches("0x");
     u32::from_str_radix(s, 16).map_err(|_| "failed to parse color")
 }
+
+fn parse_volume(s: &str) -> Result<f32, &'static str> {
+    s.parse::<f32>()
+        .ok()
+        .filter(|volume| (0.0..=1.0).contains(volume))
+        .ok_or("volume must be between 0.0 and 1.0")
+}
+
+// Format is a comma-separated list of `frame=keys`, where keys are the hex digits held down
+// Example: `30=5,45=,60=4C` presses 5 at frame 30, releases it at 45, then holds 4 and C from 60
+fn parse_key_script(s: &str) -> Result<Vec<KeyEvent>, &'static str> {
+    let mut events = s
+        .split(',')
+        .map(|event| {
+            let (frame, held) = event
+                .split_once('=')
+                .ok_or("key event must be in the form frame=keys")?;
+            let frame = frame.trim().parse().map_err(|_| "failed to parse frame")?;
+
+            let mut keys = [false; 16];
+            for key in held.trim().chars() {
+                let key = key.to_digit(16).ok_or("keys must be hex digits")?;
+                keys[key as usize] = true;
+            }
+
+            Ok(KeyEvent { frame, keys })
+        })
+        .collect::<Result<Vec<_>, &'static str>>()?;
+
+    events.sort_by_key(|event| event.frame);
+
+    Ok(events)
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashMap, fmt};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    platform::Platform,
+    quirks::{IndexIncrement, Quirks},
+};
+
+/// What the database knows about a specific ROM
+#[derive(Debug, Clone)]
+pub struct RomInfo {
+    pub title: String,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    /// Instructions per 60 Hz frame
+    pub tickrate: Option<usize>,
+    pub foreground: Option<u32>,
+    pub background: Option<u32>,
+    pub plane2: Option<u32>,
+    pub blend: Option<u32>,
+    /// Controller buttons (`up`, `a`, etc.) mapped to CHIP-8 keys
+    pub keys: HashMap<String, u8>,
+}
+
+#[derive(Debug)]
+pub enum DatabaseError {
+    Json(serde_json::Error),
+    Malformed(&'static str),
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "invalid database JSON: {err}"),
+            Self::Malformed(reason) => write!(f, "malformed database: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+/// ROM metadata keyed by SHA-1 hash, in the format of the community chip-8-database's
+/// `programs.json`
+#[derive(Debug, Default)]
+pub struct Database {
+    roms: HashMap<String, RomInfo>,
+}
+
+impl Database {
+    pub fn bundled() -> Self {
+        Self::parse(include_str!("../data/programs.json")).expect("bundled database is invalid")
+    }
+
+    pub fn parse(json: &str) -> Result<Self, DatabaseError> {
+        let programs: Value = serde_json::from_str(json).map_err(DatabaseError::Json)?;
+        let programs = programs
+            .as_array()
+            .ok_or(DatabaseError::Malformed("expected an array of programs"))?;
+
+        let mut roms = HashMap::new();
+        for program in programs {
+            let title = program
+                .get("title")
+                .and_then(Value::as_str)
+                .ok_or(DatabaseError::Malformed("program is missing a title"))?;
+            let Some(program_roms) = program.get("roms").and_then(Value::as_object) else {
+                continue;
+            };
+
+            for (hash, rom) in program_roms {
+                // ROMs only for platforms we can't run (like MEGA-CHIP) are left out
+                if let Some(info) = parse_rom(title, rom) {
+                    roms.insert(hash.to_ascii_lowercase(), info);
+                }
+            }
+        }
+
+        Ok(Self { roms })
+    }
+
+    pub fn lookup(&self, hash: &str) -> Option<&RomInfo> {
+        self.roms.get(hash)
+    }
+}
+
+/// Lowercase hex SHA-1 of a program, as used for database lookups
+pub fn rom_hash(prg: &[u8]) -> String {
+    sha1_smol::Sha1::from(prg).digest().to_string()
+}
+
+fn parse_rom(title: &str, rom: &Value) -> Option<RomInfo> {
+    let (platform_id, platform) = rom
+        .get("platforms")?
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_str)
+        .find_map(|id| Some((id, Platform::from_database_id(id)?)))?;
+
+    let mut quirks = platform.quirks();
+    if let Some(overrides) = rom
+        .get("quirkyPlatforms")
+        .and_then(|quirky| quirky.get(platform_id))
+        .and_then(Value::as_object)
+    {
+        apply_quirk_overrides(&mut quirks, overrides);
+    }
+
+    let colors = rom
+        .get("colors")
+        .and_then(|colors| colors.get("pixels"))
+        .and_then(Value::as_array);
+    let color = |idx: usize| {
+        colors?
+            .get(idx)?
+            .as_str()
+            .and_then(|color| u32::from_str_radix(color.trim_start_matches('#'), 16).ok())
+    };
+
+    let keys = rom
+        .get("keys")
+        .and_then(Value::as_object)
+        .map(|keys| {
+            keys.iter()
+                .filter_map(|(button, key)| {
+                    let key = u8::try_from(key.as_u64()?).ok().filter(|&key| key < 16)?;
+                    Some((button.clone(), key))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(RomInfo {
+        title: title.to_owned(),
+        platform,
+        quirks,
+        tickrate: rom
+            .get("tickrate")
+            .and_then(Value::as_u64)
+            .and_then(|tickrate| tickrate.try_into().ok()),
+        background: color(0),
+        foreground: color(1),
+        plane2: color(2),
+        blend: color(3),
+        keys,
+    })
+}
+
+fn apply_quirk_overrides(quirks: &mut Quirks, overrides: &Map<String, Value>) {
+    // The database has two flags for what FX55/FX65 do to I, which are only meaningful together
+    let mut increment_by_x = quirks.index_increment == IndexIncrement::X;
+    let mut leave_i_unchanged = quirks.index_increment == IndexIncrement::Unchanged;
+
+    for (name, enabled) in overrides {
+        let Some(enabled) = enabled.as_bool() else {
+            continue;
+        };
+        match name.as_str() {
+            "shift" => quirks.shift_vx = enabled,
+            "memoryIncrementByX" => increment_by_x = enabled,
+            "memoryLeaveIUnchanged" => leave_i_unchanged = enabled,
+            "wrap" => quirks.clip_sprites = !enabled,
+            "jump" => quirks.jump_vx = enabled,
+            "vblank" => quirks.display_wait = enabled,
+            "logic" => quirks.vf_reset = enabled,
+            _ => {}
+        }
+    }
+
+    quirks.index_increment = if leave_i_unchanged {
+        IndexIncrement::Unchanged
+    } else if increment_by_x {
+        IndexIncrement::X
+    } else {
+        IndexIncrement::XPlusOne
+    };
+}
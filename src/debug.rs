@@ -0,0 +1,23 @@
+use chip8::{instructions::DisplayModified, Fault, Machine};
+
+/// What the frontend should do with the machine after handling the debugger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Execute the next instruction as usual
+    Running,
+    /// Don't execute anything, but keep the frontend responsive
+    Paused,
+    Quit,
+}
+
+/// A user interface to a [`chip8::debugger::Debugger`], driven by the window or headless loop
+pub trait DebugFrontend {
+    /// Stops at breakpoints and handles any waiting commands, without blocking
+    fn poll(&mut self, machine: &mut Machine) -> State;
+    /// Same as [`DebugFrontend::poll`], but waits for commands while paused
+    fn wait(&mut self, machine: &mut Machine) -> State;
+    /// Pauses to inspect a fault
+    fn fault(&mut self, machine: &Machine, fault: &Fault);
+    /// Whether any commands have changed the display since this was last called
+    fn take_display_modified(&mut self) -> DisplayModified;
+}
@@ -0,0 +1,180 @@
+//! Expressions like `V3 == 0x10 && I > 0x300`, checked against the machine's state
+//!
+//! Comparisons are joined with `&&` and `||`, where `&&` binds tighter. Operands are the
+//! registers `V0` to `VF`, `I`, `PC`, `SP` (the stack depth), the timers `DT` and `ST`, and
+//! decimal or `0x` prefixed hexadecimal numbers.
+
+use std::{fmt, str::FromStr};
+
+use crate::{registers::Register, Machine};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Reg(Register),
+    Index,
+    Pc,
+    Sp,
+    DelayTimer,
+    SoundTimer,
+    Num(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Term {
+    lhs: Operand,
+    comparison: Comparison,
+    rhs: Operand,
+}
+
+/// A condition for a breakpoint, see the [module docs](self) for the syntax
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    source: String,
+    /// Any of these groups of terms holding makes the condition true
+    any: Vec<Vec<Term>>,
+}
+
+impl Condition {
+    pub fn eval(&self, machine: &Machine) -> bool {
+        self.any
+            .iter()
+            .any(|all| all.iter().all(|term| term.eval(machine)))
+    }
+}
+
+impl Term {
+    fn eval(&self, machine: &Machine) -> bool {
+        let (lhs, rhs) = (self.lhs.value(machine), self.rhs.value(machine));
+        match self.comparison {
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs,
+        }
+    }
+}
+
+impl Operand {
+    fn value(self, machine: &Machine) -> u16 {
+        let chip8 = machine.chip8();
+        match self {
+            Self::Reg(reg) => u16::from(chip8.registers[reg]),
+            Self::Index => chip8.registers.index,
+            Self::Pc => chip8.pc,
+            // Unwrap is ok, the stack is tiny
+            Self::Sp => u16::try_from(chip8.stack.len()).unwrap(),
+            Self::DelayTimer => u16::from(chip8.delay_timer),
+            Self::SoundTimer => u16::from(chip8.sound_timer),
+            Self::Num(num) => num,
+        }
+    }
+}
+
+impl FromStr for Operand {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+        Ok(match upper.as_str() {
+            "I" => Self::Index,
+            "PC" => Self::Pc,
+            "SP" => Self::Sp,
+            "DT" => Self::DelayTimer,
+            "ST" => Self::SoundTimer,
+            _ => {
+                if let Some(hex) = upper.strip_prefix("0X") {
+                    Self::Num(u16::from_str_radix(hex, 16).map_err(|_| "invalid number")?)
+                } else if let Some(reg) = upper.strip_prefix('V').filter(|reg| reg.len() == 1) {
+                    let reg = u16::from_str_radix(reg, 16).map_err(|_| "unknown register")?;
+                    // Unwrap is ok, a single hex digit is a valid register
+                    Self::Reg(Register::from_u16(reg).unwrap())
+                } else {
+                    Self::Num(s.parse().map_err(|_| "expected a register or number")?)
+                }
+            }
+        })
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "==" => Ok(Self::Eq),
+            "!=" => Ok(Self::Ne),
+            "<" => Ok(Self::Lt),
+            "<=" => Ok(Self::Le),
+            ">" => Ok(Self::Gt),
+            ">=" => Ok(Self::Ge),
+            _ => Err("expected a comparison like == or <"),
+        }
+    }
+}
+
+impl FromStr for Term {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s);
+        let [lhs, comparison, rhs] = tokens.as_slice() else {
+            return Err("expected a comparison like V0 == 0x10");
+        };
+        Ok(Self {
+            lhs: lhs.parse()?,
+            comparison: comparison.parse()?,
+            rhs: rhs.parse()?,
+        })
+    }
+}
+
+impl FromStr for Condition {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let any = s
+            .split("||")
+            .map(|all| all.split("&&").map(str::parse).collect())
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            source: s.split_whitespace().collect::<Vec<_>>().join(" "),
+            any,
+        })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Splits a comparison into its operands and operator, which don't need spaces between them
+fn tokenize(s: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(first) = rest.chars().next() {
+        let len = if first.is_ascii_alphanumeric() {
+            rest.find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len())
+        } else {
+            rest.find(|c: char| c.is_ascii_alphanumeric() || c.is_whitespace())
+                .unwrap_or(rest.len())
+        };
+        tokens.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    tokens
+}
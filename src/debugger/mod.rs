@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    chip8::{AccessKind, MemAccess},
+    fault::Fault,
+    instructions::{DisplayModified, Instruction},
+    Machine,
+};
+
+mod condition;
+
+pub use condition::Condition;
+
+/// Why the debugger paused the machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint {
+        addr: u16,
+    },
+    /// The instruction at `pc` accessed watched memory
+    Watchpoint {
+        pc: u16,
+        access: MemAccess,
+    },
+    /// A condition set without an address became true
+    Condition,
+    /// A subroutine call being stepped over returned
+    StepOver,
+    /// The subroutine being finished returned
+    Finish,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Paused,
+    Running,
+    /// Running until the instruction at `addr` with `depth` return addresses on the stack
+    StepOver {
+        addr: u16,
+        depth: usize,
+    },
+    /// Running until the stack has fewer than `depth` return addresses
+    Finish {
+        depth: usize,
+    },
+}
+
+/// Which accesses to a range of memory stop the machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watch {
+    Read,
+    Write,
+    Access,
+}
+
+/// Stops after an instruction accesses any byte from `start` to `end` inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: u16,
+    pub end: u16,
+    pub watch: Watch,
+}
+
+impl Watchpoint {
+    pub const fn is_triggered_by(&self, access: &MemAccess) -> bool {
+        let kind_matches = match self.watch {
+            Watch::Read => matches!(access.kind, AccessKind::Read),
+            Watch::Write => matches!(access.kind, AccessKind::Write),
+            Watch::Access => true,
+        };
+        kind_matches && access.overlaps(self.start, self.end)
+    }
+}
+
+/// Breakpoints, watchpoints and stepping, shared by every debugging frontend
+///
+/// While the debugger isn't paused, [`Debugger::check`] should be called before every
+/// [`Machine::step`].
+#[derive(Debug)]
+pub struct Debugger {
+    /// Breakpoints by address, which only stop when their condition holds if they have one
+    breakpoints: BTreeMap<u16, Option<Condition>>,
+    /// Checked before every instruction, wherever it is
+    conditions: Vec<Condition>,
+    watchpoints: Vec<Watchpoint>,
+    mode: Mode,
+}
+
+impl Debugger {
+    /// Starts out paused, so breakpoints can be set before the program runs
+    pub const fn new() -> Self {
+        Self {
+            breakpoints: BTreeMap::new(),
+            conditions: Vec::new(),
+            watchpoints: Vec::new(),
+            mode: Mode::Paused,
+        }
+    }
+
+    /// Returns false if there was already a breakpoint at `addr`, which has its condition
+    /// replaced
+    pub fn add_breakpoint(&mut self, addr: u16, condition: Option<Condition>) -> bool {
+        self.breakpoints.insert(addr, condition).is_none()
+    }
+
+    /// Returns false if there was no breakpoint at `addr`
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr).is_some()
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = (u16, Option<&Condition>)> + '_ {
+        self.breakpoints
+            .iter()
+            .map(|(&addr, condition)| (addr, condition.as_ref()))
+    }
+
+    /// Stops before any instruction once `condition` holds
+    pub fn add_condition(&mut self, condition: Condition) {
+        self.conditions.push(condition);
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    /// Removes one watchpoint identical to `watchpoint`, returning false if there was none
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        let index = self
+            .watchpoints
+            .iter()
+            .position(|&other| other == watchpoint);
+        index.map(|index| self.watchpoints.remove(index)).is_some()
+    }
+
+    /// Removes every watchpoint starting at `start`, returning false if there were none
+    pub fn remove_watchpoints(&mut self, start: u16) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints
+            .retain(|watchpoint| watchpoint.start != start);
+        self.watchpoints.len() != len
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Removes all breakpoints, conditions and watchpoints
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.conditions.clear();
+        self.watchpoints.clear();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.mode == Mode::Paused
+    }
+
+    pub const fn pause(&mut self) {
+        self.mode = Mode::Paused;
+    }
+
+    /// Runs until the next breakpoint, first stepping past any breakpoint at the program counter
+    pub fn resume(&mut self, machine: &mut Machine) -> Result<DisplayModified, Fault> {
+        let modified = self.step(machine)?;
+        self.mode = Mode::Running;
+        Ok(modified)
+    }
+
+    /// Executes a single instruction
+    ///
+    /// A machine waiting for a vertical blank first has its timers ticked, as if a frame passed,
+    /// so stepping always makes progress.
+    pub fn step(&mut self, machine: &mut Machine) -> Result<DisplayModified, Fault> {
+        self.mode = Mode::Paused;
+        if machine.is_waiting_for_vblank() {
+            machine.tick_timers();
+        }
+        machine.step()
+    }
+
+    /// Executes a single instruction, running a subroutine call to completion instead of
+    /// stopping inside it
+    pub fn step_over(&mut self, machine: &mut Machine) -> Result<DisplayModified, Fault> {
+        let pc = machine.chip8().pc;
+        let depth = machine.chip8().stack.len();
+        let instruction = machine.instruction_at(pc);
+
+        let modified = self.step(machine)?;
+        if let Some(call @ Instruction::CallSubroutine { .. }) = instruction {
+            self.mode = Mode::StepOver {
+                addr: pc.wrapping_add(call.size()),
+                depth,
+            };
+        }
+        Ok(modified)
+    }
+
+    /// Runs until the current subroutine returns
+    pub fn finish(&mut self, machine: &mut Machine) -> Result<DisplayModified, Fault> {
+        let depth = machine.chip8().stack.len();
+        let modified = self.step(machine)?;
+        self.mode = Mode::Finish { depth };
+        Ok(modified)
+    }
+
+    /// Pauses and returns why, if the machine should stop before its next instruction
+    ///
+    /// Watchpoints are checked against the memory accessed by the previous instruction, so the
+    /// machine stops just after the access.
+    pub fn check(&mut self, machine: &Machine) -> Option<Stop> {
+        let stop = self.find_stop(machine)?;
+        self.mode = Mode::Paused;
+        Some(stop)
+    }
+
+    fn find_stop(&self, machine: &Machine) -> Option<Stop> {
+        let pc = machine.chip8().pc;
+        let depth = machine.chip8().stack.len();
+
+        let watched = machine.chip8().accesses.iter().find(|access| {
+            self.watchpoints
+                .iter()
+                .any(|watchpoint| watchpoint.is_triggered_by(access))
+        });
+        if let Some(&access) = watched {
+            return Some(Stop::Watchpoint {
+                pc: machine.last_pc(),
+                access,
+            });
+        }
+
+        if let Some(condition) = self.breakpoints.get(&pc) {
+            if condition
+                .as_ref()
+                .is_none_or(|condition| condition.eval(machine))
+            {
+                return Some(Stop::Breakpoint { addr: pc });
+            }
+        }
+
+        if self
+            .conditions
+            .iter()
+            .any(|condition| condition.eval(machine))
+        {
+            return Some(Stop::Condition);
+        }
+
+        match self.mode {
+            Mode::StepOver { addr, depth: d } if pc == addr && depth == d => Some(Stop::StepOver),
+            Mode::Finish { depth: d } if depth < d => Some(Stop::Finish),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,139 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{
+    chip8::PROGRAM_START,
+    instructions::{Instruction, Syntax},
+    platform::Platform,
+};
+
+/// A run of program bytes, either decoded as an instruction or left as data
+#[derive(Debug)]
+pub struct Line {
+    pub addr: u16,
+    pub bytes: Vec<u8>,
+    /// `None` when the bytes don't decode to an instruction the platform supports
+    pub instruction: Option<Instruction>,
+}
+
+#[derive(Debug)]
+pub struct Disassembly {
+    pub lines: Vec<Line>,
+    /// Names for jump and call targets which start a line
+    pub labels: HashMap<u16, String>,
+}
+
+/// Decodes a program loaded at [`PROGRAM_START`], 2 bytes at a time
+///
+/// This is a linear sweep, so sprites and other data embedded between instructions are decoded
+/// as instructions when they happen to look like one.
+pub fn disassemble(prg: &[u8], platform: Platform) -> Disassembly {
+    disassemble_at(prg, PROGRAM_START, platform)
+}
+
+/// Same as [`disassemble`], for bytes found at `start` in memory
+pub fn disassemble_at(prg: &[u8], start: u16, platform: Platform) -> Disassembly {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    let mut addr = start;
+
+    while offset < prg.len() {
+        let (instruction, size) = decode(&prg[offset..], platform);
+        let end = (offset + usize::from(size)).min(prg.len());
+        lines.push(Line {
+            addr,
+            bytes: prg[offset..end].to_vec(),
+            instruction,
+        });
+        offset = end;
+        addr = addr.wrapping_add(size);
+    }
+
+    let starts: Vec<u16> = lines.iter().map(|line| line.addr).collect();
+    let labels = lines
+        .iter()
+        .filter_map(|line| match line.instruction {
+            Some(
+                Instruction::Jump { addr }
+                | Instruction::CallSubroutine { addr }
+                | Instruction::JumpOffset { addr },
+            ) => Some(addr),
+            _ => None,
+        })
+        .filter(|addr| starts.binary_search(addr).is_ok())
+        .map(|addr| (addr, format!("L{addr:03X}")))
+        .collect();
+
+    Disassembly { lines, labels }
+}
+
+/// Decodes the instruction at the start of `bytes`, and how many bytes it (or the data) takes up
+fn decode(bytes: &[u8], platform: Platform) -> (Option<Instruction>, u16) {
+    let word = |i: usize| {
+        bytes
+            .get(i..i + 2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+    };
+
+    // A trailing odd byte is always data
+    let Some(first) = word(0) else {
+        return (None, 1);
+    };
+    let instruction = if Instruction::is_long(first) {
+        word(2).and_then(|next| Instruction::parse_long(first, next))
+    } else {
+        Instruction::decode(first)
+    };
+
+    instruction
+        .filter(|instruction| instruction.is_supported_on(platform))
+        .map_or((None, 2), |instruction| {
+            let size = instruction.size();
+            (Some(instruction), size)
+        })
+}
+
+impl Disassembly {
+    /// Formats the program as assembly source, with each line's address and bytes in a comment
+    pub fn listing(&self, syntax: Syntax) -> String {
+        let comment = match syntax {
+            Syntax::Cowgod => ';',
+            Syntax::Octo => '#',
+        };
+
+        let mut out = String::new();
+        for line in &self.lines {
+            if let Some(label) = self.labels.get(&line.addr) {
+                match syntax {
+                    Syntax::Cowgod => writeln!(out, "{label}:"),
+                    Syntax::Octo => writeln!(out, ": {label}"),
+                }
+                .unwrap();
+            }
+
+            let text = line.instruction.as_ref().map_or_else(
+                || data(&line.bytes, syntax),
+                |instruction| {
+                    instruction
+                        .mnemonic(syntax)
+                        .with_labels(&self.labels)
+                        .to_string()
+                },
+            );
+            write!(out, "    {text:<28}{comment} {:#05X}  ", line.addr).unwrap();
+            for byte in &line.bytes {
+                write!(out, "{byte:02X}").unwrap();
+            }
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+fn data(bytes: &[u8], syntax: Syntax) -> String {
+    let bytes: Vec<String> = bytes.iter().map(|byte| format!("{byte:#04X}")).collect();
+    match syntax {
+        Syntax::Cowgod => format!("DB {}", bytes.join(", ")),
+        Syntax::Octo => bytes.join(" "),
+    }
+}
@@ -1,39 +1,221 @@
-use crate::{cli, WIDTH};
+use crate::{HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, WIDTH};
 
-#[derive(Clone, Copy)]
-pub enum Mode {
-    SetFalse,
-    Toggle,
+/// Bitmask for the first plane, the only one outside of XO-CHIP
+pub const PLANE_1: u8 = 0b01;
+/// Bitmask for the second XO-CHIP plane
+pub const PLANE_2: u8 = 0b10;
+
+/// Pixel state of the screen, independent of how it is eventually presented
+///
+/// Every plane is stored as one bit per pixel, with a `u128` per row holding the leftmost pixel
+/// in the highest bit, so sprites are drawn and scrolled a whole row at a time. Read together,
+/// each pixel holds one bit per plane, giving up to 4 distinct colors.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    /// Rows of [`PLANE_1`] and [`PLANE_2`], `height()` long each
+    planes: [Vec<u128>; 2],
+    hires: bool,
+    /// Planes affected by drawing, clearing and scrolling
+    selected_planes: u8,
 }
 
-// Returns if pixel was toggled from true to false
-pub fn write_to_buffer(
-    buf: &mut [u32],
-    x: usize,
-    y: usize,
-    mode: Mode,
-    colors: &cli::Colors,
-) -> bool {
-    assert!((0..64).contains(&x), "x coordinate out of bounds");
-    assert!((0..32).contains(&y), "y coordinate out of bounds");
-
-    let mut collision = false;
-
-    let offset = (y * WIDTH) + x;
-    let color = match mode {
-        Mode::SetFalse => colors.background,
-        Mode::Toggle => {
-            let current_state = buf[offset];
-            if current_state == colors.background {
-                colors.foreground
-            } else {
-                collision = true;
-                colors.background
+impl Framebuffer {
+    pub fn new() -> Self {
+        Self {
+            planes: [vec![0; HEIGHT], vec![0; HEIGHT]],
+            hires: false,
+            selected_planes: PLANE_1,
+        }
+    }
+
+    /// Takes row-major pixel plane bits, as given by [`Framebuffer::pixels`]
+    ///
+    /// Returns `None` if there aren't exactly enough pixels for the resolution.
+    pub(crate) fn from_raw_parts(pixels: &[u8], hires: bool, selected_planes: u8) -> Option<Self> {
+        let mut framebuffer = Self::new();
+        framebuffer.set_hires(hires);
+        framebuffer.select_planes(selected_planes);
+        let width = framebuffer.width();
+        if pixels.len() != width * framebuffer.height() {
+            return None;
+        }
+
+        for (y, row) in pixels.chunks_exact(width).enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                for (index, rows) in framebuffer.planes.iter_mut().enumerate() {
+                    if pixel & plane_bit(index) != 0 {
+                        rows[y] |= column_bit(x);
+                    }
+                }
+            }
+        }
+        Some(framebuffer)
+    }
+
+    pub const fn width(&self) -> usize {
+        if self.hires {
+            HIRES_WIDTH
+        } else {
+            WIDTH
+        }
+    }
+
+    pub const fn height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            HEIGHT
+        }
+    }
+
+    pub const fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Switches between 64x32 and 128x64 resolution, clearing every plane
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        let height = self.height();
+        self.planes = [vec![0; height], vec![0; height]];
+    }
+
+    pub const fn selected_planes(&self) -> u8 {
+        self.selected_planes
+    }
+
+    pub const fn select_planes(&mut self, planes: u8) {
+        self.selected_planes = planes & (PLANE_1 | PLANE_2);
+    }
+
+    /// Row-major pixel plane bits, `width() * height()` long
+    pub fn pixels(&self) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.width() * self.height());
+        for y in 0..self.height() {
+            pixels.extend((0..self.width()).map(|x| self.get(x, y)));
+        }
+        pixels
+    }
+
+    /// The plane bits of a pixel
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        assert!(x < self.width(), "x coordinate out of bounds");
+        self.planes
+            .iter()
+            .enumerate()
+            .filter(|(_, rows)| rows[y] & column_bit(x) != 0)
+            .fold(0, |pixel, (index, _)| pixel | plane_bit(index))
+    }
+
+    /// Clears the selected planes
+    pub fn clear(&mut self) {
+        for rows in self.selected_rows() {
+            rows.fill(0);
+        }
+    }
+
+    /// XORs a row of sprite pixels into `plane`, starting at `x` on row `y`
+    ///
+    /// `bits` holds `sprite_width` pixels with the leftmost in the highest bit. Pixels past the
+    /// right edge wrap around to the left, unless `clip` is set. Returns whether any pixel was
+    /// toggled from on to off.
+    pub fn draw_row(
+        &mut self,
+        x: usize,
+        y: usize,
+        bits: u16,
+        sprite_width: usize,
+        plane: u8,
+        clip: bool,
+    ) -> bool {
+        let width = self.width();
+        assert!(x < width, "x coordinate out of bounds");
+        assert!(y < self.height(), "y coordinate out of bounds");
+
+        let sprite = u128::from(bits) << (u128::BITS as usize - sprite_width);
+        let mut row = sprite >> x;
+        if !clip && x > 0 {
+            // What went past the right edge comes back in on the left
+            row |= sprite << (width - x);
+        }
+        row &= self.row_mask();
+
+        let mut collision = false;
+        for (index, rows) in self.planes.iter_mut().enumerate() {
+            if plane & plane_bit(index) != 0 {
+                collision |= rows[y] & row != 0;
+                rows[y] ^= row;
+            }
+        }
+        collision
+    }
+
+    /// Moves the selected planes down, leaving blank rows at the top
+    pub fn scroll_down(&mut self, amount: usize) {
+        for rows in self.selected_rows() {
+            let amount = amount.min(rows.len());
+            rows.rotate_right(amount);
+            rows[..amount].fill(0);
+        }
+    }
+
+    /// Moves the selected planes up, leaving blank rows at the bottom
+    pub fn scroll_up(&mut self, amount: usize) {
+        for rows in self.selected_rows() {
+            let amount = amount.min(rows.len());
+            rows.rotate_left(amount);
+            let len = rows.len();
+            rows[len - amount..].fill(0);
+        }
+    }
+
+    /// Moves the selected planes right, leaving blank columns on the left
+    pub fn scroll_right(&mut self, amount: usize) {
+        let mask = self.row_mask();
+        let amount = u32::try_from(amount).unwrap_or(u32::MAX);
+        for rows in self.selected_rows() {
+            for row in rows.iter_mut() {
+                *row = row.checked_shr(amount).unwrap_or(0) & mask;
             }
         }
-    };
+    }
+
+    /// Moves the selected planes left, leaving blank columns on the right
+    pub fn scroll_left(&mut self, amount: usize) {
+        let amount = u32::try_from(amount).unwrap_or(u32::MAX);
+        for rows in self.selected_rows() {
+            for row in rows.iter_mut() {
+                *row = row.checked_shl(amount).unwrap_or(0);
+            }
+        }
+    }
+
+    fn selected_rows(&mut self) -> impl Iterator<Item = &mut Vec<u128>> {
+        let selected = self.selected_planes;
+        self.planes
+            .iter_mut()
+            .enumerate()
+            .filter(move |&(index, _)| selected & plane_bit(index) != 0)
+            .map(|(_, rows)| rows)
+    }
 
-    buf[offset] = color;
+    // The bits of a row that are on screen, which is only the upper half in low resolution
+    const fn row_mask(&self) -> u128 {
+        u128::MAX << (u128::BITS as usize - self.width())
+    }
+}
+
+// The plane bitmask for an index into `Framebuffer::planes`
+const fn plane_bit(index: usize) -> u8 {
+    1 << index
+}
+
+// The bit of a row holding column `x`
+const fn column_bit(x: usize) -> u128 {
+    1 << (u128::BITS as usize - 1 - x)
+}
 
-    collision
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,28 @@
+use std::fmt;
+
+/// An unrecoverable error in the emulated program, which stops execution without taking the
+/// rest of the emulator down with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    StackOverflow,
+    StackUnderflow,
+    MemoryOutOfBounds { addr: usize },
+    PcOutOfRange { pc: u16 },
+    RomTooLarge { size: usize },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::StackOverflow => write!(f, "emulated program stack overflow"),
+            Self::StackUnderflow => write!(f, "emulated program popped from empty stack"),
+            Self::MemoryOutOfBounds { addr } => {
+                write!(f, "memory access out of bounds at address 0x{addr:X}")
+            }
+            Self::PcOutOfRange { pc } => write!(f, "program counter out of range at 0x{pc:X}"),
+            Self::RomTooLarge { size } => write!(f, "program is too large ({size} bytes)"),
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
@@ -0,0 +1,547 @@
+//! GDB remote serial protocol server, so existing debuggers can drive the emulator
+//!
+//! The register file is V0 to VF, then I, PC and SP (the stack depth), in that order and in
+//! CHIP-8's big-endian byte order. Target memory is the whole of [`chip8::chip8::Chip8::mem`].
+
+use std::{
+    fmt::Write as _,
+    io::{self, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+};
+
+use chip8::{
+    chip8::AccessKind,
+    debugger::{Debugger, Stop, Watch, Watchpoint},
+    instructions::DisplayModified,
+    registers::Register,
+    Fault, Machine,
+};
+use log::{info, warn};
+
+use crate::debug::{DebugFrontend, State};
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+/// Register numbers after V0 to VF
+const REG_I: usize = 16;
+const REG_PC: usize = 17;
+const REG_SP: usize = 18;
+
+// Signals reported when the machine stops
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+enum Message {
+    Packet(String),
+    /// The checksum didn't match, so GDB should send it again
+    Corrupt,
+    /// GDB wants a running program stopped
+    Interrupt,
+}
+
+/// Debugger controlled by GDB over a TCP connection
+pub struct GdbStub {
+    debugger: Debugger,
+    stream: TcpStream,
+    messages: Receiver<Message>,
+    /// GDB asked for packets not to be acknowledged, as TCP is already reliable
+    no_ack: bool,
+    /// GDB is gone, so the program runs without stopping
+    detached: bool,
+    /// GDB asked to kill the program
+    quit: bool,
+    /// Set when a command changes the display, until the frontend takes it
+    display_modified: DisplayModified,
+}
+
+impl GdbStub {
+    /// Waits for GDB to connect on the given localhost port
+    pub fn listen(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        info!("Waiting for GDB on {}", listener.local_addr()?);
+        let (stream, addr) = listener.accept()?;
+        info!("GDB connected from {addr}");
+        stream.set_nodelay(true)?;
+
+        // Reading blocks, so it gets its own thread
+        let (sender, messages) = mpsc::channel();
+        let reader = stream.try_clone()?;
+        std::thread::spawn(move || read_messages(reader, &sender));
+
+        Ok(Self {
+            debugger: Debugger::new(),
+            stream,
+            messages,
+            no_ack: false,
+            detached: false,
+            quit: false,
+            display_modified: DisplayModified::Unchanged,
+        })
+    }
+
+    fn update(&mut self, machine: &mut Machine, block: bool) -> State {
+        loop {
+            if self.detached {
+                return State::Running;
+            }
+
+            if !self.debugger.is_paused() {
+                if let Some(stop) = self.debugger.check(machine) {
+                    let reply = self.stop_reply(stop);
+                    self.send(&reply);
+                } else {
+                    // In all-stop mode GDB sends nothing but interrupts while the program runs
+                    match self.messages.try_recv() {
+                        Ok(Message::Interrupt) => {
+                            self.debugger.pause();
+                            self.send(&format!("S{SIGINT:02x}"));
+                        }
+                        Err(TryRecvError::Disconnected) => self.detach(),
+                        _ => return State::Running,
+                    }
+                }
+            }
+
+            // Continuing has already executed an instruction, which needs checking before any
+            // more run
+            if let Some(state) = self.read_packets(machine, block) {
+                return state;
+            }
+        }
+    }
+
+    /// Handles packets until one resumes the machine, or returns what to do if there are no
+    /// more for now
+    fn read_packets(&mut self, machine: &mut Machine, block: bool) -> Option<State> {
+        while self.debugger.is_paused() && !self.detached {
+            let message = if block {
+                self.messages.recv().map_err(|_| TryRecvError::Disconnected)
+            } else {
+                self.messages.try_recv()
+            };
+
+            match message {
+                Ok(Message::Packet(packet)) => {
+                    self.ack(b'+');
+                    if let Some(reply) = self.handle(&packet, machine) {
+                        self.send(&reply);
+                    }
+                    if self.quit {
+                        return Some(State::Quit);
+                    }
+                }
+                Ok(Message::Corrupt) => self.ack(b'-'),
+                // Already stopped
+                Ok(Message::Interrupt) => {}
+                Err(TryRecvError::Empty) => return Some(State::Paused),
+                Err(TryRecvError::Disconnected) => self.detach(),
+            }
+        }
+        None
+    }
+
+    /// Returns the reply to send, or `None` if the reply is sent once the machine stops
+    fn handle(&mut self, packet: &str, machine: &mut Machine) -> Option<String> {
+        let (command, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+
+        let reply = match command {
+            "?" => format!("S{SIGTRAP:02x}"),
+            "g" => read_registers(machine),
+            "G" => ok(write_registers(args, machine)),
+            "p" => read_register(args, machine).unwrap_or_else(error),
+            "P" => ok(write_register(args, machine)),
+            "m" => read_memory(args, machine).unwrap_or_else(error),
+            "M" => ok(write_memory(args, machine)),
+            "Z" => ok(self.insert_point(args)),
+            "z" => ok(self.remove_point(args)),
+            "c" => return self.resume(args, machine, Debugger::resume),
+            "s" => {
+                let reply = self.resume(args, machine, Debugger::step);
+                return reply.or_else(|| Some(format!("S{SIGTRAP:02x}")));
+            }
+            "D" => {
+                self.send("OK");
+                self.detach();
+                return None;
+            }
+            "k" => {
+                self.quit = true;
+                return None;
+            }
+            // Only one thread, which is always selected and alive
+            "H" | "T" => "OK".to_owned(),
+            "q" | "Q" => self.query(packet),
+            // Anything else, such as vCont and binary X writes, is unsupported, so GDB falls back
+            // to the packets above
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+
+    fn query(&mut self, packet: &str) -> String {
+        if let Some(annex) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            return read_target_xml(annex).unwrap_or_else(error);
+        }
+
+        let name = packet.split([':', ',']).next().unwrap_or_default();
+        match name {
+            "qSupported" => "PacketSize=1000;qXfer:features:read+;QStartNoAckMode+".to_owned(),
+            "QStartNoAckMode" => {
+                // This packet itself is still acknowledged
+                self.no_ack = true;
+                "OK".to_owned()
+            }
+            "qAttached" => "1".to_owned(),
+            "qC" => "QC1".to_owned(),
+            "qfThreadInfo" => "m1".to_owned(),
+            "qsThreadInfo" => "l".to_owned(),
+            "qSymbol" => "OK".to_owned(),
+            _ => String::new(),
+        }
+    }
+
+    /// Runs one of the [`Debugger`]'s methods, returning a stop reply if it faulted
+    fn resume(
+        &mut self,
+        args: &str,
+        machine: &mut Machine,
+        start: fn(&mut Debugger, &mut Machine) -> Result<DisplayModified, Fault>,
+    ) -> Option<String> {
+        // Resuming from another address
+        if let Ok(addr) = u16::from_str_radix(args, 16) {
+            machine.chip8_mut().pc = addr;
+        }
+
+        match start(&mut self.debugger, machine) {
+            Ok(modified) => {
+                if modified == DisplayModified::Changed {
+                    self.display_modified = DisplayModified::Changed;
+                }
+                None
+            }
+            Err(fault) => {
+                warn!("Program faulted: {fault}");
+                Some(format!("S{SIGSEGV:02x}"))
+            }
+        }
+    }
+
+    fn insert_point(&mut self, args: &str) -> Option<()> {
+        let (kind, addr, len) = parse_point(args)?;
+        match kind {
+            '0' => {
+                self.debugger.add_breakpoint(addr, None);
+            }
+            _ => self.debugger.add_watchpoint(watchpoint(kind, addr, len)?),
+        }
+        Some(())
+    }
+
+    fn remove_point(&mut self, args: &str) -> Option<()> {
+        let (kind, addr, len) = parse_point(args)?;
+        // Removing something that isn't there is fine, as GDB may retry after an error
+        match kind {
+            '0' => {
+                self.debugger.remove_breakpoint(addr);
+            }
+            _ => {
+                self.debugger
+                    .remove_watchpoint(watchpoint(kind, addr, len)?);
+            }
+        }
+        Some(())
+    }
+
+    fn stop_reply(&self, stop: Stop) -> String {
+        let Stop::Watchpoint { access, .. } = stop else {
+            return format!("S{SIGTRAP:02x}");
+        };
+
+        // GDB looks up the watchpoint by the address it reports
+        let watchpoint = self
+            .debugger
+            .watchpoints()
+            .iter()
+            .find(|watchpoint| watchpoint.is_triggered_by(&access));
+        let addr = watchpoint.map_or(access.addr, |watchpoint| access.addr.max(watchpoint.start));
+        let name = match (watchpoint.map(|watchpoint| watchpoint.watch), access.kind) {
+            (Some(Watch::Access), _) => "awatch",
+            (_, AccessKind::Read) => "rwatch",
+            (_, AccessKind::Write) => "watch",
+        };
+        format!("T{SIGTRAP:02x}{name}:{addr:x};")
+    }
+
+    /// Stops debugging and lets the program run freely
+    fn detach(&mut self) {
+        info!("GDB detached");
+        self.detached = true;
+        self.debugger.clear();
+    }
+
+    fn ack(&mut self, byte: u8) {
+        if !self.no_ack {
+            self.write(&[byte]);
+        }
+    }
+
+    fn send(&mut self, data: &str) {
+        let packet = format!("${data}#{:02x}", checksum(data.as_bytes()));
+        self.write(packet.as_bytes());
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        // A broken connection shows up as a disconnect on the reading side
+        if let Err(err) = self.stream.write_all(bytes) {
+            warn!("Failed to write to GDB: {err}");
+        }
+    }
+}
+
+impl DebugFrontend for GdbStub {
+    fn poll(&mut self, machine: &mut Machine) -> State {
+        self.update(machine, false)
+    }
+
+    fn wait(&mut self, machine: &mut Machine) -> State {
+        self.update(machine, true)
+    }
+
+    fn fault(&mut self, _machine: &Machine, fault: &Fault) {
+        if self.detached {
+            return;
+        }
+        warn!("Program faulted: {fault}");
+        self.debugger.pause();
+        self.send(&format!("S{SIGSEGV:02x}"));
+    }
+
+    fn take_display_modified(&mut self) -> DisplayModified {
+        std::mem::replace(&mut self.display_modified, DisplayModified::Unchanged)
+    }
+}
+
+impl Drop for GdbStub {
+    fn drop(&mut self) {
+        // GDB is waiting for the running program to stop, and it never will now
+        if !self.detached && !self.debugger.is_paused() {
+            self.send("W00");
+        }
+    }
+}
+
+/// Splits the byte stream from GDB into packets and interrupts
+fn read_messages(stream: TcpStream, sender: &Sender<Message>) {
+    let mut bytes = BufReader::new(stream).bytes().map_while(Result::ok);
+
+    while let Some(byte) = bytes.next() {
+        let message = match byte {
+            0x03 => Message::Interrupt,
+            b'$' => {
+                let data: Vec<u8> = bytes.by_ref().take_while(|&byte| byte != b'#').collect();
+                let sum: Vec<u8> = bytes.by_ref().take(2).collect();
+                let valid = std::str::from_utf8(&sum)
+                    .ok()
+                    .and_then(|sum| u8::from_str_radix(sum, 16).ok())
+                    == Some(checksum(&data));
+                if valid {
+                    Message::Packet(String::from_utf8_lossy(&data).into_owned())
+                } else {
+                    Message::Corrupt
+                }
+            }
+            // Acknowledgements, since nothing is ever resent
+            _ => continue,
+        };
+
+        if sender.send(message).is_err() {
+            return;
+        }
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn ok(result: Option<()>) -> String {
+    result.map_or_else(error, |()| "OK".to_owned())
+}
+
+fn error() -> String {
+    "E01".to_owned()
+}
+
+/// Parses `Z`/`z` packets in the form `type,addr,kind`, where kind is the length for
+/// watchpoints
+fn parse_point(args: &str) -> Option<(char, u16, u16)> {
+    let mut fields = args.split(',');
+    let kind = fields.next()?.chars().next()?;
+    let addr = u16::from_str_radix(fields.next()?, 16).ok()?;
+    let len = u16::from_str_radix(fields.next()?, 16).ok()?;
+    Some((kind, addr, len))
+}
+
+fn watchpoint(kind: char, start: u16, len: u16) -> Option<Watchpoint> {
+    let watch = match kind {
+        '2' => Watch::Write,
+        '3' => Watch::Read,
+        '4' => Watch::Access,
+        // Hardware breakpoints aren't supported, GDB falls back to software ones
+        _ => return None,
+    };
+    Some(Watchpoint {
+        start,
+        end: start.saturating_add(len.max(1) - 1),
+        watch,
+    })
+}
+
+/// V0 to VF by register number
+fn v_register(reg: usize) -> Option<Register> {
+    u16::try_from(reg)
+        .ok()
+        .filter(|&reg| reg < 16)
+        .and_then(Register::from_u16)
+}
+
+/// The register's value and its size in bytes
+fn register_value(machine: &Machine, reg: usize) -> Option<(u16, usize)> {
+    let chip8 = machine.chip8();
+    if let Some(reg) = v_register(reg) {
+        return Some((u16::from(chip8.registers[reg]), 1));
+    }
+    Some(match reg {
+        REG_I => (chip8.registers.index, 2),
+        REG_PC => (chip8.pc, 2),
+        // Unwrap is ok, the stack is tiny
+        REG_SP => (u16::try_from(chip8.stack.len()).unwrap(), 1),
+        _ => return None,
+    })
+}
+
+fn encode_register(value: u16, size: usize) -> String {
+    if size == 1 {
+        format!("{value:02x}")
+    } else {
+        format!("{value:04x}")
+    }
+}
+
+fn read_registers(machine: &Machine) -> String {
+    (0..=REG_SP)
+        .filter_map(|reg| register_value(machine, reg))
+        .map(|(value, size)| encode_register(value, size))
+        .collect()
+}
+
+fn read_register(args: &str, machine: &Machine) -> Option<String> {
+    let reg = usize::from_str_radix(args, 16).ok()?;
+    let (value, size) = register_value(machine, reg)?;
+    Some(encode_register(value, size))
+}
+
+fn set_register(machine: &mut Machine, reg: usize, value: u16) -> Option<()> {
+    let chip8 = machine.chip8_mut();
+    if let Some(reg) = v_register(reg) {
+        chip8.registers[reg] = u8::try_from(value).ok()?;
+        return Some(());
+    }
+    match reg {
+        REG_I => chip8.registers.index = value,
+        REG_PC => chip8.pc = value,
+        // The stack depth is read-only
+        _ => return None,
+    }
+    Some(())
+}
+
+fn write_register(args: &str, machine: &mut Machine) -> Option<()> {
+    let (reg, value) = args.split_once('=')?;
+    let reg = usize::from_str_radix(reg, 16).ok()?;
+    let (_, size) = register_value(machine, reg)?;
+    let value = u16::from_str_radix(value.get(..size * 2)?, 16).ok()?;
+    set_register(machine, reg, value)
+}
+
+fn write_registers(args: &str, machine: &mut Machine) -> Option<()> {
+    let mut rest = args;
+    for reg in 0..REG_SP {
+        let (_, size) = register_value(machine, reg)?;
+        let value = u16::from_str_radix(rest.get(..size * 2)?, 16).ok()?;
+        set_register(machine, reg, value)?;
+        rest = &rest[size * 2..];
+    }
+    Some(())
+}
+
+/// Parses `addr,len`
+fn parse_range(args: &str) -> Option<(usize, usize)> {
+    let (addr, len) = args.split_once(',')?;
+    Some((
+        usize::from_str_radix(addr, 16).ok()?,
+        usize::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+fn read_memory(args: &str, machine: &Machine) -> Option<String> {
+    let (addr, len) = parse_range(args)?;
+    let bytes = machine.chip8().mem_range(addr, len).ok()?;
+    Some(bytes.iter().fold(String::new(), |mut hex, byte| {
+        // Writing to a string can't fail
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    }))
+}
+
+fn write_memory(args: &str, machine: &mut Machine) -> Option<()> {
+    let (range, data) = args.split_once(':')?;
+    let (addr, len) = parse_range(range)?;
+    let bytes = (0..len)
+        .map(|idx| u8::from_str_radix(data.get(idx * 2..idx * 2 + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    machine
+        .chip8_mut()
+        .mem_range_mut(addr, len)
+        .ok()?
+        .copy_from_slice(&bytes);
+    Some(())
+}
+
+/// Handles `offset,length` reads of the target description
+fn read_target_xml(args: &str) -> Option<String> {
+    let (offset, len) = parse_range(args)?;
+    let rest = TARGET_XML.get(offset.min(TARGET_XML.len())..)?;
+    Some(if rest.len() > len {
+        format!("m{}", &rest[..len])
+    } else {
+        format!("l{rest}")
+    })
+}
@@ -0,0 +1,158 @@
+use std::ops::ControlFlow;
+
+use chip8::{
+    audio::{AudioSink, Beeper},
+    display::{Framebuffer, PLANE_1, PLANE_2},
+    Fault, Machine,
+};
+use log::{error, info};
+
+use crate::{
+    cli,
+    debug::{DebugFrontend, State},
+    tape::Tape,
+};
+
+/// Runs the machine without a window, then prints the final screen to stdout
+///
+/// Returns the fault that stopped the program early, if any. With the debugger, faults pause the
+/// program instead.
+pub fn run(
+    mut machine: Machine,
+    headless: &cli::Headless,
+    tickrate: usize,
+    mut beeper: Beeper,
+    mut audio_sinks: Vec<Box<dyn AudioSink>>,
+    mut debugger: Option<Box<dyn DebugFrontend>>,
+    mut tape: Option<Tape>,
+) -> Result<(), Fault> {
+    let mut key_events = headless.keys.iter().peekable();
+    let mut keys = [false; 16];
+
+    let frames = headless.frames.unwrap_or_else(|| {
+        tape.as_ref()
+            .and_then(Tape::remaining_frames)
+            .and_then(|frames| u32::try_from(frames).ok())
+            .unwrap_or(600)
+    });
+
+    let mut frame = 0;
+    // After a fault, the debugger resumes the rest of the frame
+    let mut resuming = false;
+    let mut instructions = 0;
+    while frame < frames {
+        while let Some(event) = key_events.next_if(|event| event.frame <= frame) {
+            keys = event.keys;
+        }
+        if !resuming {
+            machine.set_keys(tape.as_mut().map_or(keys, |tape| tape.begin_frame(keys)));
+            instructions = tape
+                .as_ref()
+                .and_then(Tape::instructions_left)
+                .map_or(tickrate, |left| usize::try_from(left).unwrap_or(usize::MAX));
+        }
+        resuming = false;
+
+        let result = run_frame(
+            &mut machine,
+            &mut instructions,
+            &mut beeper,
+            &mut audio_sinks,
+            &mut debugger,
+            &mut tape,
+        );
+        match (result, debugger.as_mut()) {
+            (Ok(ControlFlow::Continue(())), _) => {
+                if let Some(tape) = tape.as_mut() {
+                    tape.end_frame();
+                }
+            }
+            (Ok(ControlFlow::Break(())), _) => break,
+            (Err(fault), Some(debugger)) => {
+                debugger.fault(&machine, &fault);
+                resuming = true;
+                continue;
+            }
+            (Err(fault), None) => {
+                error!("Program faulted at frame {frame}: {fault}");
+                dump(&machine, headless.dump);
+                return Err(fault);
+            }
+        }
+        frame += 1;
+
+        // Let any final beep play out, so it ends up in recordings. A debugger may still want to
+        // inspect a halted program, so only the frame limit stops it then.
+        if debugger.is_none() && machine.is_halted() && !machine.sound_active() {
+            info!("Program halted after {frame} frames");
+            break;
+        }
+    }
+
+    dump(&machine, headless.dump);
+    Ok(())
+}
+
+// Like Machine::run_frame, but generating audio before the sound timer is decremented
+// Breaks if the debugger quits. Counts down `instructions`, so a frame cut short by a fault can
+// carry on where it left off.
+fn run_frame(
+    machine: &mut Machine,
+    instructions: &mut usize,
+    beeper: &mut Beeper,
+    audio_sinks: &mut Vec<Box<dyn AudioSink>>,
+    debugger: &mut Option<Box<dyn DebugFrontend>>,
+    tape: &mut Option<Tape>,
+) -> Result<ControlFlow<()>, Fault> {
+    while *instructions > 0 {
+        // Blocks until the debugger is told to run
+        if let Some(debugger) = debugger {
+            if debugger.wait(machine) == State::Quit {
+                return Ok(ControlFlow::Break(()));
+            }
+        }
+        // Like in the window, the instruction that faults uses up its place in the frame
+        *instructions -= 1;
+        if let Some(tape) = tape {
+            tape.step();
+        }
+        machine.step()?;
+    }
+    beeper.render_frame(machine.sound(), audio_sinks);
+    machine.tick_timers();
+    Ok(ControlFlow::Continue(()))
+}
+
+fn dump(machine: &Machine, format: cli::DumpFormat) {
+    match format {
+        cli::DumpFormat::Ascii => print!("{}", to_ascii(machine.framebuffer())),
+        cli::DumpFormat::Hash => println!("{:016x}", hash(machine.framebuffer())),
+    }
+}
+
+fn to_ascii(framebuffer: &Framebuffer) -> String {
+    let mut out = String::with_capacity((framebuffer.width() + 1) * framebuffer.height());
+    for y in 0..framebuffer.height() {
+        for x in 0..framebuffer.width() {
+            // Extra characters for the XO-CHIP planes
+            out.push(match framebuffer.get(x, y) {
+                0 => '.',
+                PLANE_1 => '#',
+                PLANE_2 => 'o',
+                _ => '@',
+            });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// 64-bit FNV-1a, stable across runs and platforms unlike std's hasher
+fn hash(framebuffer: &Framebuffer) -> u64 {
+    framebuffer
+        .pixels()
+        .into_iter()
+        .fold(0xCBF2_9CE4_8422_2325, |hash, pixel| {
+            (hash ^ u64::from(pixel)).wrapping_mul(0x0100_0000_01B3)
+        })
+}
@@ -1,6 +1,6 @@
-use crate::registers::Register;
+use crate::{platform::Platform, registers::Register};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
     ClearDisplay,
     ReturnSubroutine,
@@ -118,4 +118,89 @@ pub enum Instruction {
     LoadMem {
         outreg_max: Register,
     },
+    // SUPER-CHIP
+    ScrollDown {
+        amount: u8,
+    },
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    LowRes,
+    HighRes,
+    GetBigFontChar {
+        inreg: Register,
+    },
+    StoreFlags {
+        inreg_max: Register,
+    },
+    LoadFlags {
+        outreg_max: Register,
+    },
+    // XO-CHIP
+    ScrollUp {
+        amount: u8,
+    },
+    StoreRange {
+        reg1: Register,
+        reg2: Register,
+    },
+    LoadRange {
+        reg1: Register,
+        reg2: Register,
+    },
+    /// Takes up 4 bytes, with the address in the second half
+    LongSetIndex {
+        val: u16,
+    },
+    SelectPlanes {
+        planes: u8,
+    },
+    LoadAudioPattern,
+    SetPitch {
+        inreg: Register,
+    },
+}
+
+impl Instruction {
+    /// Whether the platform's interpreter understands this instruction
+    pub const fn is_supported_on(&self, platform: Platform) -> bool {
+        match self {
+            Self::ScrollDown { .. }
+            | Self::ScrollRight
+            | Self::ScrollLeft
+            | Self::Exit
+            | Self::LowRes
+            | Self::HighRes
+            | Self::GetBigFontChar { .. }
+            | Self::StoreFlags { .. }
+            | Self::LoadFlags { .. }
+            // DXY0 draws a 16x16 sprite, rather than nothing
+            | Self::Display { height: 0, .. } => {
+                matches!(platform, Platform::SuperChip | Platform::XoChip)
+            }
+            Self::ScrollUp { .. }
+            | Self::StoreRange { .. }
+            | Self::LoadRange { .. }
+            | Self::LongSetIndex { .. }
+            | Self::SelectPlanes { .. }
+            | Self::LoadAudioPattern
+            | Self::SetPitch { .. } => matches!(platform, Platform::XoChip),
+            _ => true,
+        }
+    }
+}
+
+impl Instruction {
+    /// Whether the instruction starting with `instruction` continues into the next 2 bytes
+    pub const fn is_long(instruction: u16) -> bool {
+        instruction == 0xF000
+    }
+
+    /// Number of bytes the instruction takes up in memory
+    pub const fn size(&self) -> u16 {
+        match self {
+            Self::LongSetIndex { .. } => 4,
+            _ => 2,
+        }
+    }
 }
@@ -1,79 +1,88 @@
-use minifb::Window;
-
-use crate::{chip8::Chip8, cli, keypad, registers::Register, HEIGHT, WIDTH};
+use crate::{
+    chip8::{AccessKind, Chip8},
+    display::{Framebuffer, PLANE_1, PLANE_2},
+    fault::Fault,
+    keypad::Keypad,
+    quirks::{IndexIncrement, Quirks},
+    registers::Register,
+};
 
 use super::Instruction;
 
-fn clear_display(display_buf: &mut [u32], colors: &cli::Colors) {
-    for y in 0..HEIGHT {
-        for x in 0..WIDTH {
-            crate::display::write_to_buffer(
-                display_buf,
-                x,
-                y,
-                crate::display::Mode::SetFalse,
-                colors,
-            );
-        }
-    }
+fn clear_display(framebuffer: &mut Framebuffer) {
+    // Only clears the selected planes
+    framebuffer.clear();
 }
 
 fn set(chip8: &mut Chip8, reg: Register, val: u8) {
     chip8.registers[reg] = val;
 }
 
-fn set_index(chip8: &mut Chip8, val: u16) {
+const fn set_index(chip8: &mut Chip8, val: u16) {
     chip8.registers.index = val;
 }
 
 fn display(
     chip8: &mut Chip8,
-    display_buf: &mut [u32],
+    framebuffer: &mut Framebuffer,
     xreg: Register,
     yreg: Register,
     height: u8,
-    colors: &cli::Colors,
-) {
-    let sprite_data = &chip8.mem
-        [chip8.registers.index as usize..chip8.registers.index as usize + height as usize];
+    quirks: Quirks,
+) -> Result<(), Fault> {
+    // DXY0 draws a 16x16 sprite, with two bytes per row
+    let (rows, sprite_width) = if height == 0 {
+        (16, 16)
+    } else {
+        (height as usize, 8)
+    };
+    // Each selected plane has its own sprite data, one after the other
+    let planes: Vec<u8> = [PLANE_1, PLANE_2]
+        .into_iter()
+        .filter(|&plane| framebuffer.selected_planes() & plane != 0)
+        .collect();
+    let plane_len = rows * sprite_width / 8;
+    let index = chip8.registers.index as usize;
+    let sprite_data = chip8.mem_range(index, plane_len * planes.len())?.to_vec();
+    chip8.record_access(AccessKind::Read, index, sprite_data.len());
+    let screen_width = framebuffer.width();
+    let screen_height = framebuffer.height();
     // Module width and height to allow for wrapping
-    let start_x = chip8.registers[xreg] as usize % WIDTH;
-    let start_y = chip8.registers[yreg] as usize % HEIGHT;
-    // Small routine to extract the bits of the individual values
-    let sprite_data = sprite_data
-        .iter()
-        .map(|x| {
-            // Reverse bits because returned array would otherwise be backwarks
-            // (indexing from the right)
-            let x = x.reverse_bits();
-            // Bitwise and with shifted '1' to extract bits
-            // Example: 0b011001001 & 0b00001000
-            let arr: [bool; 8] = std::array::from_fn(|idx| x & (1 << idx) != 0);
-
-            arr.into_iter().enumerate()
-        })
-        .enumerate();
-
-    for (y_offset, line) in sprite_data {
-        if start_y + y_offset < HEIGHT {
-            for (x_offset, should_toggle) in line {
-                if should_toggle && start_x + x_offset < WIDTH {
-                    let collision = crate::display::write_to_buffer(
-                        display_buf,
-                        start_x + x_offset,
-                        start_y + y_offset,
-                        crate::display::Mode::Toggle,
-                        colors,
-                    );
-                    // Set VF register to either 1 or 0, depending on whether 2 sprites collided
-                    chip8.registers[Register::VF] = u8::from(collision);
-                }
+    let start_x = chip8.registers[xreg] as usize % screen_width;
+    let start_y = chip8.registers[yreg] as usize % screen_height;
+
+    // Rows that collided on any plane, or were cut off at the bottom
+    let mut hit_rows = [false; 16];
+    let mut collision = false;
+    for (plane, plane_data) in planes.into_iter().zip(sprite_data.chunks_exact(plane_len)) {
+        for (y_offset, row) in plane_data.chunks_exact(sprite_width / 8).enumerate() {
+            let y = start_y + y_offset;
+            if y >= screen_height && quirks.clip_sprites {
+                hit_rows[y_offset] = true;
+                continue;
+            }
+            // Combine the row's bytes, so the leftmost pixel is the highest bit
+            let bits = row
+                .iter()
+                .fold(0_u16, |acc, &byte| (acc << 8) | u16::from(byte));
+            let y = y % screen_height;
+            if framebuffer.draw_row(start_x, y, bits, sprite_width, plane, quirks.clip_sprites) {
+                hit_rows[y_offset] = true;
+                collision = true;
             }
         }
     }
+
+    chip8.registers[Register::VF] = if quirks.collision_rows && framebuffer.is_hires() {
+        // Unwrap is ok, there are at most 16 rows
+        u8::try_from(hit_rows.iter().filter(|&&hit| hit).count()).unwrap()
+    } else {
+        u8::from(collision)
+    };
+    Ok(())
 }
 
-fn jump(chip8: &mut Chip8, addr: u16) {
+const fn jump(chip8: &mut Chip8, addr: u16) {
     chip8.pc = addr;
 }
 
@@ -82,37 +91,49 @@ fn add(chip8: &mut Chip8, reg: Register, val: u8) {
     chip8.registers[reg] = chip8.registers[reg].wrapping_add(val);
 }
 
-fn call_subroutine(chip8: &mut Chip8, addr: u16) {
-    chip8.stack.push(chip8.pc);
+fn call_subroutine(chip8: &mut Chip8, addr: u16) -> Result<(), Fault> {
+    chip8.stack.push(chip8.pc)?;
     chip8.pc = addr;
+    Ok(())
 }
 
-fn return_subroutine(chip8: &mut Chip8) {
-    let addr = chip8.stack.pop();
+fn return_subroutine(chip8: &mut Chip8) -> Result<(), Fault> {
+    let addr = chip8.stack.pop()?;
     chip8.pc = addr;
+    Ok(())
+}
+
+// Skips over the next instruction, which is 4 bytes long for an XO-CHIP long index load
+fn skip(chip8: &mut Chip8) {
+    let next = chip8
+        .mem_range(chip8.pc as usize, 2)
+        // Unwrap is ok, guaranteed to be correct size
+        .map_or(0, |bytes| u16::from_be_bytes(bytes.try_into().unwrap()));
+    let size = if Instruction::is_long(next) { 4 } else { 2 };
+    chip8.pc = chip8.pc.wrapping_add(size);
 }
 
 fn skip_eq(chip8: &mut Chip8, reg: Register, num: u8) {
     if chip8.registers[reg] == num {
-        chip8.pc += 2;
+        skip(chip8);
     }
 }
 
 fn skip_ne(chip8: &mut Chip8, reg: Register, num: u8) {
     if chip8.registers[reg] != num {
-        chip8.pc += 2;
+        skip(chip8);
     }
 }
 
 fn skip_eq_reg(chip8: &mut Chip8, reg1: Register, reg2: Register) {
     if chip8.registers[reg1] == chip8.registers[reg2] {
-        chip8.pc += 2;
+        skip(chip8);
     }
 }
 
 fn skip_ne_reg(chip8: &mut Chip8, reg1: Register, reg2: Register) {
     if chip8.registers[reg1] != chip8.registers[reg2] {
-        chip8.pc += 2;
+        skip(chip8);
     }
 }
 
@@ -120,16 +141,25 @@ fn set_reg(chip8: &mut Chip8, reg1: Register, reg2: Register) {
     chip8.registers[reg1] = chip8.registers[reg2];
 }
 
-fn or(chip8: &mut Chip8, reg1: Register, reg2: Register) {
+fn or(chip8: &mut Chip8, reg1: Register, reg2: Register, quirks: Quirks) {
     chip8.registers[reg1] |= chip8.registers[reg2];
+    reset_vf(chip8, quirks);
 }
 
-fn and(chip8: &mut Chip8, reg1: Register, reg2: Register) {
+fn and(chip8: &mut Chip8, reg1: Register, reg2: Register, quirks: Quirks) {
     chip8.registers[reg1] &= chip8.registers[reg2];
+    reset_vf(chip8, quirks);
 }
 
-fn xor(chip8: &mut Chip8, reg1: Register, reg2: Register) {
+fn xor(chip8: &mut Chip8, reg1: Register, reg2: Register, quirks: Quirks) {
     chip8.registers[reg1] ^= chip8.registers[reg2];
+    reset_vf(chip8, quirks);
+}
+
+fn reset_vf(chip8: &mut Chip8, quirks: Quirks) {
+    if quirks.vf_reset {
+        chip8.registers[Register::VF] = 0;
+    }
 }
 
 fn add_reg(chip8: &mut Chip8, reg1: Register, reg2: Register) {
@@ -152,37 +182,68 @@ fn sub2(chip8: &mut Chip8, reg1: Register, reg2: Register) {
     chip8.registers[Register::VF] = u8::from(!overflow);
 }
 
-fn shr(chip8: &mut Chip8, reg1: Register, reg2: Register) {
-    let num = chip8.registers[reg2];
+fn shr(chip8: &mut Chip8, reg1: Register, reg2: Register, quirks: Quirks) {
+    let num = chip8.registers[if quirks.shift_vx { reg1 } else { reg2 }];
     chip8.registers[reg1] = num >> 1;
     // Check if bit that was shifted is a 1 or a 0
     let shifted = u8::from(num & 0b0000_0001 != 0);
     chip8.registers[Register::VF] = shifted;
 }
 
-fn shl(chip8: &mut Chip8, reg1: Register, reg2: Register) {
-    let num = chip8.registers[reg2];
+fn shl(chip8: &mut Chip8, reg1: Register, reg2: Register, quirks: Quirks) {
+    let num = chip8.registers[if quirks.shift_vx { reg1 } else { reg2 }];
     chip8.registers[reg1] = num << 1;
     // Check if bit that was shifted is a 1 or a 0
     let shifted = u8::from(num & 0b1000_0000 != 0);
     chip8.registers[Register::VF] = shifted;
 }
 
-fn load_mem(chip8: &mut Chip8, outreg_max: Register) {
+fn load_mem(chip8: &mut Chip8, outreg_max: Register, quirks: Quirks) -> Result<(), Fault> {
+    let index = chip8.registers.index as usize;
+    // Check the whole range first, so a fault doesn't leave registers half-loaded
+    let len = Register::iter_until(outreg_max).count();
+    chip8.mem_range(index, len)?;
+    chip8.record_access(AccessKind::Read, index, len);
+
     let reg_iter = Register::iter_until(outreg_max);
     for (idx, reg) in reg_iter.enumerate() {
-        chip8.registers[reg] = chip8.mem[chip8.registers.index as usize + idx];
+        chip8.registers[reg] = chip8.mem[index + idx];
     }
+    increment_index(chip8, outreg_max, quirks);
+    Ok(())
 }
 
-fn store_mem(chip8: &mut Chip8, inreg_max: Register) {
+fn store_mem(chip8: &mut Chip8, inreg_max: Register, quirks: Quirks) -> Result<(), Fault> {
+    let index = chip8.registers.index as usize;
+    // Check the whole range first, so a fault doesn't leave memory half-written
+    let len = Register::iter_until(inreg_max).count();
+    chip8.mem_range(index, len)?;
+    chip8.record_access(AccessKind::Write, index, len);
+
     let reg_iter = Register::iter_until(inreg_max);
     for (idx, reg) in reg_iter.enumerate() {
-        chip8.mem[chip8.registers.index as usize + idx] = chip8.registers[reg];
+        chip8.mem[index + idx] = chip8.registers[reg];
     }
-}
-
-fn bin_to_dec(chip8: &mut Chip8, inreg: Register) {
+    increment_index(chip8, inreg_max, quirks);
+    Ok(())
+}
+
+fn increment_index(chip8: &mut Chip8, reg_max: Register, quirks: Quirks) {
+    // Number of registers accessed, minus one
+    let last = Register::iter_until(reg_max).count() - 1;
+    let increment = match quirks.index_increment {
+        IndexIncrement::Unchanged => return,
+        IndexIncrement::X => last,
+        IndexIncrement::XPlusOne => last + 1,
+    };
+    // Unwrap is ok, there are only 16 registers
+    chip8.registers.index = chip8
+        .registers
+        .index
+        .wrapping_add(increment.try_into().unwrap());
+}
+
+fn bin_to_dec(chip8: &mut Chip8, inreg: Register) -> Result<(), Fault> {
     let num = chip8.registers[inreg];
     let digit1 = num / 100;
     let digit2 = num / 10 % 10;
@@ -190,17 +251,22 @@ fn bin_to_dec(chip8: &mut Chip8, inreg: Register) {
 
     let index = chip8.registers.index as usize;
 
-    chip8.mem[index] = digit1;
-    chip8.mem[index + 1] = digit2;
-    chip8.mem[index + 2] = digit3;
+    chip8
+        .mem_range_mut(index, 3)?
+        .copy_from_slice(&[digit1, digit2, digit3]);
+    chip8.record_access(AccessKind::Write, index, 3);
+    Ok(())
 }
 
 fn add_to_index(chip8: &mut Chip8, inreg: Register) {
-    chip8.registers.index += u16::from(chip8.registers[inreg]);
+    chip8.registers.index = chip8
+        .registers
+        .index
+        .wrapping_add(u16::from(chip8.registers[inreg]));
 }
 
 fn rand(chip8: &mut Chip8, outreg: Register, val: u8) {
-    let rand = fastrand::u8(u8::MIN..u8::MAX) & val;
+    let rand = chip8.rng.u8(..) & val;
     chip8.registers[outreg] = rand;
 }
 
@@ -212,10 +278,16 @@ fn set_delay_timer(chip8: &mut Chip8, inreg: Register) {
     chip8.delay_timer = chip8.registers[inreg];
 }
 
-fn wait_for_key(chip8: &mut Chip8, window: &Window, keyreg: Register) {
-    match keypad::get_pressed_key(window) {
+// The COSMAC VIP waits for a key to be pressed and then released
+fn wait_for_key(chip8: &mut Chip8, keypad: &mut Keypad, keyreg: Register, quirks: Quirks) {
+    let key = if quirks.wait_key_press {
+        keypad.get_pressed_key()
+    } else {
+        keypad.take_released_key()
+    };
+    match key {
         Some(key) => chip8.registers[keyreg] = key,
-        None => chip8.pc -= 2,
+        None => chip8.pc = chip8.pc.wrapping_sub(2),
     }
 }
 
@@ -226,28 +298,113 @@ fn get_font_char(chip8: &mut Chip8, inreg: Register) {
     chip8.registers.index = addr;
 }
 
-fn jump_offset(chip8: &mut Chip8, addr: u16) {
-    chip8.pc = addr + u16::from(chip8.registers[Register::V0]);
+fn get_big_font_char(chip8: &mut Chip8, inreg: Register) {
+    let key_offset = u16::from(chip8.registers[inreg]);
+    // Big font data starts at 0xA0 and each key takes up 0xA bytes
+    let addr = 0xA0 + key_offset * 0xA;
+    chip8.registers.index = addr;
+}
+
+fn store_flags(chip8: &mut Chip8, inreg_max: Register) {
+    for (idx, reg) in Register::iter_until(inreg_max).enumerate() {
+        chip8.flags[idx] = chip8.registers[reg];
+    }
+}
+
+fn load_flags(chip8: &mut Chip8, outreg_max: Register) {
+    for (idx, reg) in Register::iter_until(outreg_max).enumerate() {
+        chip8.registers[reg] = chip8.flags[idx];
+    }
+}
+
+// Registers from reg1 to reg2 inclusive, which may be in descending order
+fn register_range(reg1: Register, reg2: Register) -> Vec<Register> {
+    let (start, end) = (reg1 as u16, reg2 as u16);
+    let indices: Vec<u16> = if start <= end {
+        (start..=end).collect()
+    } else {
+        (end..=start).rev().collect()
+    };
+    // Unwrap is ok, indices come from valid registers
+    indices
+        .into_iter()
+        .map(|idx| Register::from_u16(idx).unwrap())
+        .collect()
+}
+
+fn store_range(chip8: &mut Chip8, reg1: Register, reg2: Register) -> Result<(), Fault> {
+    let values: Vec<u8> = register_range(reg1, reg2)
+        .into_iter()
+        .map(|reg| chip8.registers[reg])
+        .collect();
+    // Unlike FX55, I is never incremented
+    let index = chip8.registers.index as usize;
+    chip8
+        .mem_range_mut(index, values.len())?
+        .copy_from_slice(&values);
+    chip8.record_access(AccessKind::Write, index, values.len());
+    Ok(())
+}
+
+fn load_range(chip8: &mut Chip8, reg1: Register, reg2: Register) -> Result<(), Fault> {
+    let range = register_range(reg1, reg2);
+    let index = chip8.registers.index as usize;
+    let values = chip8.mem_range(index, range.len())?.to_vec();
+    chip8.record_access(AccessKind::Read, index, values.len());
+    for (reg, val) in range.into_iter().zip(values) {
+        chip8.registers[reg] = val;
+    }
+    Ok(())
+}
+
+fn load_audio_pattern(chip8: &mut Chip8) -> Result<(), Fault> {
+    let index = chip8.registers.index as usize;
+    let pattern = chip8.mem_range(index, 16)?;
+    // Unwrap is ok, guaranteed to be correct size
+    chip8.audio_pattern = Some(pattern.try_into().unwrap());
+    chip8.record_access(AccessKind::Read, index, 16);
+    Ok(())
+}
+
+fn set_pitch(chip8: &mut Chip8, inreg: Register) {
+    chip8.audio_pitch = chip8.registers[inreg];
+}
+
+fn jump_offset(chip8: &mut Chip8, addr: u16, quirks: Quirks) {
+    let reg = if quirks.jump_vx {
+        // Unwrap is ok, the address is only 12 bits long
+        Register::from_u16(addr >> 8).unwrap()
+    } else {
+        Register::V0
+    };
+    chip8.pc = addr + u16::from(chip8.registers[reg]);
 }
 
 fn set_sound_timer(chip8: &mut Chip8, inreg: Register) {
     chip8.sound_timer = chip8.registers[inreg];
 }
 
-fn skip_if_key(chip8: &mut Chip8, window: &Window, keyreg: Register) {
+fn skip_if_key(chip8: &mut Chip8, keypad: &Keypad, keyreg: Register) {
     // If key is invalid, assume it isn't pressed
-    if keypad::is_key_pressed(window, chip8.registers[keyreg]).unwrap_or(false) {
-        chip8.pc += 2;
+    if keypad
+        .is_key_pressed(chip8.registers[keyreg])
+        .unwrap_or(false)
+    {
+        skip(chip8);
     }
 }
 
-fn skip_if_not_key(chip8: &mut Chip8, window: &Window, keyreg: Register) {
+fn skip_if_not_key(chip8: &mut Chip8, keypad: &Keypad, keyreg: Register) {
     // If key is invalid, assume it isn't pressed
-    if !keypad::is_key_pressed(window, chip8.registers[keyreg]).unwrap_or(false) {
-        chip8.pc += 2;
+    if !keypad
+        .is_key_pressed(chip8.registers[keyreg])
+        .unwrap_or(false)
+    {
+        skip(chip8);
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DisplayModified {
     Unchanged,
     Changed,
@@ -257,55 +414,89 @@ impl Instruction {
     pub fn execute(
         &self,
         chip8: &mut Chip8,
-        display_buf: &mut [u32],
-        window: &Window,
-        colors: &cli::Colors,
-    ) -> DisplayModified {
+        framebuffer: &mut Framebuffer,
+        keypad: &mut Keypad,
+        quirks: Quirks,
+    ) -> Result<DisplayModified, Fault> {
         let mut modified = DisplayModified::Unchanged;
 
         match *self {
             Self::ClearDisplay => {
-                clear_display(display_buf, colors);
+                clear_display(framebuffer);
                 modified = DisplayModified::Changed;
             }
             Self::Set { reg, val } => set(chip8, reg, val),
-            Self::SetIndex { val } => set_index(chip8, val),
+            Self::SetIndex { val } | Self::LongSetIndex { val } => set_index(chip8, val),
             Self::Display { xreg, yreg, height } => {
-                display(chip8, display_buf, xreg, yreg, height, colors);
+                display(chip8, framebuffer, xreg, yreg, height, quirks)?;
                 modified = DisplayModified::Changed;
             }
             Self::Jump { addr } => jump(chip8, addr),
             Self::Add { reg, val } => add(chip8, reg, val),
-            Self::CallSubroutine { addr } => call_subroutine(chip8, addr),
-            Self::ReturnSubroutine => return_subroutine(chip8),
+            Self::CallSubroutine { addr } => call_subroutine(chip8, addr)?,
+            Self::ReturnSubroutine => return_subroutine(chip8)?,
             Self::SkipEq { reg, num } => skip_eq(chip8, reg, num),
             Self::SkipNe { reg, num } => skip_ne(chip8, reg, num),
             Self::SkipEqReg { reg1, reg2 } => skip_eq_reg(chip8, reg1, reg2),
             Self::SkipNeReg { reg1, reg2 } => skip_ne_reg(chip8, reg1, reg2),
             Self::SetReg { reg1, reg2 } => set_reg(chip8, reg1, reg2),
-            Self::Or { reg1, reg2 } => or(chip8, reg1, reg2),
-            Self::And { reg1, reg2 } => and(chip8, reg1, reg2),
-            Self::Xor { reg1, reg2 } => xor(chip8, reg1, reg2),
+            Self::Or { reg1, reg2 } => or(chip8, reg1, reg2, quirks),
+            Self::And { reg1, reg2 } => and(chip8, reg1, reg2, quirks),
+            Self::Xor { reg1, reg2 } => xor(chip8, reg1, reg2, quirks),
             Self::AddReg { reg1, reg2 } => add_reg(chip8, reg1, reg2),
             Self::Sub1 { reg1, reg2 } => sub1(chip8, reg1, reg2),
             Self::Sub2 { reg1, reg2 } => sub2(chip8, reg1, reg2),
-            Self::Shr { reg1, reg2 } => shr(chip8, reg1, reg2),
-            Self::Shl { reg1, reg2 } => shl(chip8, reg1, reg2),
-            Self::LoadMem { outreg_max } => load_mem(chip8, outreg_max),
-            Self::StoreMem { inreg_max } => store_mem(chip8, inreg_max),
-            Self::BinToDec { inreg } => bin_to_dec(chip8, inreg),
+            Self::Shr { reg1, reg2 } => shr(chip8, reg1, reg2, quirks),
+            Self::Shl { reg1, reg2 } => shl(chip8, reg1, reg2, quirks),
+            Self::LoadMem { outreg_max } => load_mem(chip8, outreg_max, quirks)?,
+            Self::StoreMem { inreg_max } => store_mem(chip8, inreg_max, quirks)?,
+            Self::BinToDec { inreg } => bin_to_dec(chip8, inreg)?,
             Self::AddToIndex { inreg } => add_to_index(chip8, inreg),
             Self::Rand { outreg, val } => rand(chip8, outreg, val),
             Self::GetDelayTimer { outreg } => get_delay_timer(chip8, outreg),
             Self::SetDelayTimer { inreg } => set_delay_timer(chip8, inreg),
-            Self::WaitForKey { keyreg } => wait_for_key(chip8, window, keyreg),
+            Self::WaitForKey { keyreg } => wait_for_key(chip8, keypad, keyreg, quirks),
             Self::GetFontChar { inreg } => get_font_char(chip8, inreg),
-            Self::JumpOffset { addr } => jump_offset(chip8, addr),
+            Self::JumpOffset { addr } => jump_offset(chip8, addr, quirks),
             Self::SetSoundTimer { inreg } => set_sound_timer(chip8, inreg),
-            Self::SkipIfKey { keyreg } => skip_if_key(chip8, window, keyreg),
-            Self::SkipIfNotKey { keyreg } => skip_if_not_key(chip8, window, keyreg),
+            Self::SkipIfKey { keyreg } => skip_if_key(chip8, keypad, keyreg),
+            Self::SkipIfNotKey { keyreg } => skip_if_not_key(chip8, keypad, keyreg),
+            Self::ScrollDown { amount } => {
+                framebuffer.scroll_down(amount as usize);
+                modified = DisplayModified::Changed;
+            }
+            Self::ScrollRight => {
+                framebuffer.scroll_right(4);
+                modified = DisplayModified::Changed;
+            }
+            Self::ScrollLeft => {
+                framebuffer.scroll_left(4);
+                modified = DisplayModified::Changed;
+            }
+            // Handled by the machine, as it stops execution entirely
+            Self::Exit => {}
+            Self::LowRes => {
+                framebuffer.set_hires(false);
+                modified = DisplayModified::Changed;
+            }
+            Self::HighRes => {
+                framebuffer.set_hires(true);
+                modified = DisplayModified::Changed;
+            }
+            Self::GetBigFontChar { inreg } => get_big_font_char(chip8, inreg),
+            Self::StoreFlags { inreg_max } => store_flags(chip8, inreg_max),
+            Self::LoadFlags { outreg_max } => load_flags(chip8, outreg_max),
+            Self::ScrollUp { amount } => {
+                framebuffer.scroll_up(amount as usize);
+                modified = DisplayModified::Changed;
+            }
+            Self::StoreRange { reg1, reg2 } => store_range(chip8, reg1, reg2)?,
+            Self::LoadRange { reg1, reg2 } => load_range(chip8, reg1, reg2)?,
+            Self::SelectPlanes { planes } => framebuffer.select_planes(planes),
+            Self::LoadAudioPattern => load_audio_pattern(chip8)?,
+            Self::SetPitch { inreg } => set_pitch(chip8, inreg),
         }
 
-        modified
+        Ok(modified)
     }
 }
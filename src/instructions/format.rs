@@ -0,0 +1,200 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::registers::Register;
+
+use super::Instruction;
+
+/// Assembly language used when formatting instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// Mnemonics from Cowgod's Chip-8 Technical Reference, such as `LD V0, 0x05`
+    #[default]
+    Cowgod,
+    /// Statements understood by the Octo assembler, such as `v0 := 0x05`
+    Octo,
+}
+
+impl FromStr for Syntax {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cowgod" => Ok(Self::Cowgod),
+            "octo" => Ok(Self::Octo),
+            _ => Err("syntax must be 'cowgod' or 'octo'"),
+        }
+    }
+}
+
+/// An instruction formatted in a given [`Syntax`], created with [`Instruction::mnemonic`]
+pub struct Mnemonic<'a> {
+    instruction: &'a Instruction,
+    syntax: Syntax,
+    labels: Option<&'a HashMap<u16, String>>,
+}
+
+impl<'a> Mnemonic<'a> {
+    /// Names jump and call targets found in `labels`, instead of showing their address
+    #[must_use]
+    pub const fn with_labels(mut self, labels: &'a HashMap<u16, String>) -> Self {
+        self.labels = Some(labels);
+        self
+    }
+
+    fn target(&self, addr: u16) -> String {
+        self.labels
+            .and_then(|labels| labels.get(&addr))
+            .cloned()
+            .unwrap_or_else(|| format!("{addr:#05X}"))
+    }
+}
+
+impl Instruction {
+    pub const fn mnemonic(&self, syntax: Syntax) -> Mnemonic<'_> {
+        Mnemonic {
+            instruction: self,
+            syntax,
+            labels: None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.mnemonic(Syntax::Cowgod).fmt(f)
+    }
+}
+
+impl fmt::Display for Mnemonic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self.syntax {
+            Syntax::Cowgod => self.cowgod(),
+            Syntax::Octo => self.octo(),
+        };
+        f.pad(&text)
+    }
+}
+
+impl Mnemonic<'_> {
+    fn cowgod(&self) -> String {
+        use Instruction as I;
+
+        match *self.instruction {
+            I::ClearDisplay => "CLS".into(),
+            I::ReturnSubroutine => "RET".into(),
+            I::Jump { addr } => format!("JP {}", self.target(addr)),
+            I::CallSubroutine { addr } => format!("CALL {}", self.target(addr)),
+            I::SkipEq { reg, num } => format!("SE {reg}, {num:#04X}"),
+            I::SkipNe { reg, num } => format!("SNE {reg}, {num:#04X}"),
+            I::SkipEqReg { reg1, reg2 } => format!("SE {reg1}, {reg2}"),
+            I::SkipNeReg { reg1, reg2 } => format!("SNE {reg1}, {reg2}"),
+            I::Set { reg, val } => format!("LD {reg}, {val:#04X}"),
+            I::Add { reg, val } => format!("ADD {reg}, {val:#04X}"),
+            I::SetReg { reg1, reg2 } => format!("LD {reg1}, {reg2}"),
+            I::Or { reg1, reg2 } => format!("OR {reg1}, {reg2}"),
+            I::And { reg1, reg2 } => format!("AND {reg1}, {reg2}"),
+            I::Xor { reg1, reg2 } => format!("XOR {reg1}, {reg2}"),
+            I::AddReg { reg1, reg2 } => format!("ADD {reg1}, {reg2}"),
+            I::Sub1 { reg1, reg2 } => format!("SUB {reg1}, {reg2}"),
+            I::Sub2 { reg1, reg2 } => format!("SUBN {reg1}, {reg2}"),
+            I::Shr { reg1, reg2 } => format!("SHR {reg1}, {reg2}"),
+            I::Shl { reg1, reg2 } => format!("SHL {reg1}, {reg2}"),
+            I::SetIndex { val } => format!("LD I, {val:#05X}"),
+            I::JumpOffset { addr } => format!("JP V0, {}", self.target(addr)),
+            I::Rand { outreg, val } => format!("RND {outreg}, {val:#04X}"),
+            I::Display { xreg, yreg, height } => format!("DRW {xreg}, {yreg}, {height:#X}"),
+            I::SkipIfKey { keyreg } => format!("SKP {keyreg}"),
+            I::SkipIfNotKey { keyreg } => format!("SKNP {keyreg}"),
+            I::GetDelayTimer { outreg } => format!("LD {outreg}, DT"),
+            I::SetDelayTimer { inreg } => format!("LD DT, {inreg}"),
+            I::SetSoundTimer { inreg } => format!("LD ST, {inreg}"),
+            I::AddToIndex { inreg } => format!("ADD I, {inreg}"),
+            I::WaitForKey { keyreg } => format!("LD {keyreg}, K"),
+            I::GetFontChar { inreg } => format!("LD F, {inreg}"),
+            I::BinToDec { inreg } => format!("LD B, {inreg}"),
+            I::StoreMem { inreg_max } => format!("LD [I], {inreg_max}"),
+            I::LoadMem { outreg_max } => format!("LD {outreg_max}, [I]"),
+            I::ScrollDown { amount } => format!("SCD {amount:#X}"),
+            I::ScrollRight => "SCR".into(),
+            I::ScrollLeft => "SCL".into(),
+            I::Exit => "EXIT".into(),
+            I::LowRes => "LOW".into(),
+            I::HighRes => "HIGH".into(),
+            I::GetBigFontChar { inreg } => format!("LD HF, {inreg}"),
+            I::StoreFlags { inreg_max } => format!("LD R, {inreg_max}"),
+            I::LoadFlags { outreg_max } => format!("LD {outreg_max}, R"),
+            I::ScrollUp { amount } => format!("SCU {amount:#X}"),
+            I::StoreRange { reg1, reg2 } => format!("SAVE {reg1}, {reg2}"),
+            I::LoadRange { reg1, reg2 } => format!("LOAD {reg1}, {reg2}"),
+            I::LongSetIndex { val } => format!("LD I, LONG {val:#06X}"),
+            I::SelectPlanes { planes } => format!("PLANE {planes:#X}"),
+            I::LoadAudioPattern => "AUDIO".into(),
+            I::SetPitch { inreg } => format!("PITCH {inreg}"),
+        }
+    }
+
+    fn octo(&self) -> String {
+        use Instruction as I;
+
+        // Octo's conditionals say when the next instruction runs, which is when it isn't skipped
+        match *self.instruction {
+            I::ClearDisplay => "clear".into(),
+            I::ReturnSubroutine => "return".into(),
+            I::Jump { addr } => format!("jump {}", self.target(addr)),
+            I::CallSubroutine { addr } => format!(":call {}", self.target(addr)),
+            I::SkipEq { reg, num } => format!("if {} != {num:#04X} then", octo(reg)),
+            I::SkipNe { reg, num } => format!("if {} == {num:#04X} then", octo(reg)),
+            I::SkipEqReg { reg1, reg2 } => format!("if {} != {} then", octo(reg1), octo(reg2)),
+            I::SkipNeReg { reg1, reg2 } => format!("if {} == {} then", octo(reg1), octo(reg2)),
+            I::Set { reg, val } => format!("{} := {val:#04X}", octo(reg)),
+            I::Add { reg, val } => format!("{} += {val:#04X}", octo(reg)),
+            I::SetReg { reg1, reg2 } => format!("{} := {}", octo(reg1), octo(reg2)),
+            I::Or { reg1, reg2 } => format!("{} |= {}", octo(reg1), octo(reg2)),
+            I::And { reg1, reg2 } => format!("{} &= {}", octo(reg1), octo(reg2)),
+            I::Xor { reg1, reg2 } => format!("{} ^= {}", octo(reg1), octo(reg2)),
+            I::AddReg { reg1, reg2 } => format!("{} += {}", octo(reg1), octo(reg2)),
+            I::Sub1 { reg1, reg2 } => format!("{} -= {}", octo(reg1), octo(reg2)),
+            I::Sub2 { reg1, reg2 } => format!("{} =- {}", octo(reg1), octo(reg2)),
+            I::Shr { reg1, reg2 } => format!("{} >>= {}", octo(reg1), octo(reg2)),
+            I::Shl { reg1, reg2 } => format!("{} <<= {}", octo(reg1), octo(reg2)),
+            I::SetIndex { val } => format!("i := {val:#05X}"),
+            I::JumpOffset { addr } => format!("jump0 {}", self.target(addr)),
+            I::Rand { outreg, val } => format!("{} := random {val:#04X}", octo(outreg)),
+            I::Display { xreg, yreg, height } => {
+                format!("sprite {} {} {height:#X}", octo(xreg), octo(yreg))
+            }
+            I::SkipIfKey { keyreg } => format!("if {} -key then", octo(keyreg)),
+            I::SkipIfNotKey { keyreg } => format!("if {} key then", octo(keyreg)),
+            I::GetDelayTimer { outreg } => format!("{} := delay", octo(outreg)),
+            I::SetDelayTimer { inreg } => format!("delay := {}", octo(inreg)),
+            I::SetSoundTimer { inreg } => format!("buzzer := {}", octo(inreg)),
+            I::AddToIndex { inreg } => format!("i += {}", octo(inreg)),
+            I::WaitForKey { keyreg } => format!("{} := key", octo(keyreg)),
+            I::GetFontChar { inreg } => format!("i := hex {}", octo(inreg)),
+            I::BinToDec { inreg } => format!("bcd {}", octo(inreg)),
+            I::StoreMem { inreg_max } => format!("save {}", octo(inreg_max)),
+            I::LoadMem { outreg_max } => format!("load {}", octo(outreg_max)),
+            I::ScrollDown { amount } => format!("scroll-down {amount:#X}"),
+            I::ScrollRight => "scroll-right".into(),
+            I::ScrollLeft => "scroll-left".into(),
+            I::Exit => "exit".into(),
+            I::LowRes => "lores".into(),
+            I::HighRes => "hires".into(),
+            I::GetBigFontChar { inreg } => format!("i := bighex {}", octo(inreg)),
+            I::StoreFlags { inreg_max } => format!("saveflags {}", octo(inreg_max)),
+            I::LoadFlags { outreg_max } => format!("loadflags {}", octo(outreg_max)),
+            I::ScrollUp { amount } => format!("scroll-up {amount:#X}"),
+            I::StoreRange { reg1, reg2 } => format!("save {} - {}", octo(reg1), octo(reg2)),
+            I::LoadRange { reg1, reg2 } => format!("load {} - {}", octo(reg1), octo(reg2)),
+            I::LongSetIndex { val } => format!("i := long {val:#06X}"),
+            I::SelectPlanes { planes } => format!("plane {planes}"),
+            I::LoadAudioPattern => "audio".into(),
+            I::SetPitch { inreg } => format!("pitch := {}", octo(inreg)),
+        }
+    }
+}
+
+/// Octo writes registers in lowercase
+fn octo(reg: Register) -> String {
+    reg.to_string().to_lowercase()
+}
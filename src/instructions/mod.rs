@@ -1,6 +1,9 @@
 mod definition;
 mod execute;
+mod format;
+mod opcodes;
 mod parse;
 
 pub use definition::Instruction;
 pub use execute::DisplayModified;
+pub use format::{Mnemonic, Syntax};
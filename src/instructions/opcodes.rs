@@ -0,0 +1,211 @@
+//! The single table of instruction encodings, used both to parse and to encode instructions
+
+use crate::registers::Register;
+
+use super::Instruction;
+
+/// A field packed into the bits an opcode's mask leaves free
+trait Operand {
+    type Value;
+
+    /// Reads the field from an instruction, where `next` is the 2 bytes following it
+    fn extract(instruction: u16, next: u16) -> Self::Value;
+    /// Bits to set in the instruction and in the 2 bytes following it
+    fn insert(value: Self::Value) -> (u16, u16);
+}
+
+/// Register in the second nibble
+struct RegX;
+/// Register in the third nibble
+struct RegY;
+/// Fourth nibble
+struct Nibble;
+/// Second byte
+struct Byte;
+/// Last three nibbles
+struct Addr;
+/// Plane mask in the second nibble, where a register would usually be
+struct Planes;
+/// The whole 2 bytes following the instruction
+struct Long;
+
+impl Operand for RegX {
+    type Value = Register;
+
+    fn extract(instruction: u16, _next: u16) -> Register {
+        // Unwrap is safe because of bitmask
+        Register::from_u16((instruction >> 8) & 0xF).unwrap()
+    }
+
+    fn insert(value: Register) -> (u16, u16) {
+        ((value as u16) << 8, 0)
+    }
+}
+
+impl Operand for RegY {
+    type Value = Register;
+
+    fn extract(instruction: u16, _next: u16) -> Register {
+        // Unwrap is safe because of bitmask
+        Register::from_u16((instruction >> 4) & 0xF).unwrap()
+    }
+
+    fn insert(value: Register) -> (u16, u16) {
+        ((value as u16) << 4, 0)
+    }
+}
+
+impl Operand for Nibble {
+    type Value = u8;
+
+    fn extract(instruction: u16, _next: u16) -> u8 {
+        (instruction & 0xF) as u8
+    }
+
+    fn insert(value: u8) -> (u16, u16) {
+        (u16::from(value) & 0xF, 0)
+    }
+}
+
+impl Operand for Byte {
+    type Value = u8;
+
+    fn extract(instruction: u16, _next: u16) -> u8 {
+        (instruction & 0xFF) as u8
+    }
+
+    fn insert(value: u8) -> (u16, u16) {
+        (u16::from(value), 0)
+    }
+}
+
+impl Operand for Addr {
+    type Value = u16;
+
+    fn extract(instruction: u16, _next: u16) -> u16 {
+        instruction & 0xFFF
+    }
+
+    fn insert(value: u16) -> (u16, u16) {
+        (value & 0xFFF, 0)
+    }
+}
+
+impl Operand for Planes {
+    type Value = u8;
+
+    fn extract(instruction: u16, _next: u16) -> u8 {
+        ((instruction >> 8) & 0xF) as u8
+    }
+
+    fn insert(value: u8) -> (u16, u16) {
+        ((u16::from(value) & 0xF) << 8, 0)
+    }
+}
+
+impl Operand for Long {
+    type Value = u16;
+
+    fn extract(_instruction: u16, next: u16) -> u16 {
+        next
+    }
+
+    fn insert(value: u16) -> (u16, u16) {
+        (0, value)
+    }
+}
+
+/// Generates the decoder and [`Instruction::encode`] from a list of
+/// `bits / mask => Variant { field: Operand }` entries, checked in order
+macro_rules! opcodes {
+    ($($bits:literal / $mask:literal => $variant:ident $({ $($field:ident: $operand:ident),* })?,)*) => {
+        /// Looks up the instruction in the table, where `next` is only read by long instructions
+        pub(super) fn decode(instruction: u16, next: u16) -> Option<Instruction> {
+            $(
+                if instruction & $mask == $bits {
+                    return Some(Instruction::$variant $({
+                        $($field: $operand::extract(instruction, next)),*
+                    })?);
+                }
+            )*
+            None
+        }
+
+        impl Instruction {
+            /// The instruction's bytes in memory, [`Instruction::size`] bytes long
+            pub fn encode(&self) -> Vec<u8> {
+                let parts: &[(u16, u16)] = match *self {
+                    $(
+                        Self::$variant $({ $($field),* })? => {
+                            &[($bits, 0) $($(, $operand::insert($field))*)?]
+                        }
+                    )*
+                };
+                let (instruction, next) = parts
+                    .iter()
+                    .fold((0, 0), |(instruction, next), part| {
+                        (instruction | part.0, next | part.1)
+                    });
+
+                let mut bytes = instruction.to_be_bytes().to_vec();
+                if self.size() == 4 {
+                    bytes.extend(next.to_be_bytes());
+                }
+                bytes
+            }
+        }
+    };
+}
+
+opcodes! {
+    0x00E0 / 0xFFFF => ClearDisplay,
+    0x00EE / 0xFFFF => ReturnSubroutine,
+    0x00C0 / 0xFFF0 => ScrollDown { amount: Nibble },
+    0x00D0 / 0xFFF0 => ScrollUp { amount: Nibble },
+    0x00FB / 0xFFFF => ScrollRight,
+    0x00FC / 0xFFFF => ScrollLeft,
+    0x00FD / 0xFFFF => Exit,
+    0x00FE / 0xFFFF => LowRes,
+    0x00FF / 0xFFFF => HighRes,
+    0x1000 / 0xF000 => Jump { addr: Addr },
+    0x2000 / 0xF000 => CallSubroutine { addr: Addr },
+    0x3000 / 0xF000 => SkipEq { reg: RegX, num: Byte },
+    0x4000 / 0xF000 => SkipNe { reg: RegX, num: Byte },
+    0x5000 / 0xF00F => SkipEqReg { reg1: RegX, reg2: RegY },
+    0x5002 / 0xF00F => StoreRange { reg1: RegX, reg2: RegY },
+    0x5003 / 0xF00F => LoadRange { reg1: RegX, reg2: RegY },
+    0x6000 / 0xF000 => Set { reg: RegX, val: Byte },
+    0x7000 / 0xF000 => Add { reg: RegX, val: Byte },
+    0x8000 / 0xF00F => SetReg { reg1: RegX, reg2: RegY },
+    0x8001 / 0xF00F => Or { reg1: RegX, reg2: RegY },
+    0x8002 / 0xF00F => And { reg1: RegX, reg2: RegY },
+    0x8003 / 0xF00F => Xor { reg1: RegX, reg2: RegY },
+    0x8004 / 0xF00F => AddReg { reg1: RegX, reg2: RegY },
+    0x8005 / 0xF00F => Sub1 { reg1: RegX, reg2: RegY },
+    0x8006 / 0xF00F => Shr { reg1: RegX, reg2: RegY },
+    0x8007 / 0xF00F => Sub2 { reg1: RegX, reg2: RegY },
+    0x800E / 0xF00F => Shl { reg1: RegX, reg2: RegY },
+    0x9000 / 0xF00F => SkipNeReg { reg1: RegX, reg2: RegY },
+    0xA000 / 0xF000 => SetIndex { val: Addr },
+    0xB000 / 0xF000 => JumpOffset { addr: Addr },
+    0xC000 / 0xF000 => Rand { outreg: RegX, val: Byte },
+    0xD000 / 0xF000 => Display { xreg: RegX, yreg: RegY, height: Nibble },
+    0xE09E / 0xF0FF => SkipIfKey { keyreg: RegX },
+    0xE0A1 / 0xF0FF => SkipIfNotKey { keyreg: RegX },
+    0xF000 / 0xFFFF => LongSetIndex { val: Long },
+    0xF001 / 0xF0FF => SelectPlanes { planes: Planes },
+    0xF002 / 0xFFFF => LoadAudioPattern,
+    0xF007 / 0xF0FF => GetDelayTimer { outreg: RegX },
+    0xF00A / 0xF0FF => WaitForKey { keyreg: RegX },
+    0xF015 / 0xF0FF => SetDelayTimer { inreg: RegX },
+    0xF018 / 0xF0FF => SetSoundTimer { inreg: RegX },
+    0xF01E / 0xF0FF => AddToIndex { inreg: RegX },
+    0xF029 / 0xF0FF => GetFontChar { inreg: RegX },
+    0xF030 / 0xF0FF => GetBigFontChar { inreg: RegX },
+    0xF033 / 0xF0FF => BinToDec { inreg: RegX },
+    0xF03A / 0xF0FF => SetPitch { inreg: RegX },
+    0xF055 / 0xF0FF => StoreMem { inreg_max: RegX },
+    0xF065 / 0xF0FF => LoadMem { outreg_max: RegX },
+    0xF075 / 0xF0FF => StoreFlags { inreg_max: RegX },
+    0xF085 / 0xF0FF => LoadFlags { outreg_max: RegX },
+}
@@ -0,0 +1,192 @@
+use std::{collections::HashMap, path::Path};
+
+use log::info;
+use minifb::{Key, Window};
+use serde_json::Value;
+
+/// Host keys by their minifb names, matched case-insensitively
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("Key0", Key::Key0),
+    ("Key1", Key::Key1),
+    ("Key2", Key::Key2),
+    ("Key3", Key::Key3),
+    ("Key4", Key::Key4),
+    ("Key5", Key::Key5),
+    ("Key6", Key::Key6),
+    ("Key7", Key::Key7),
+    ("Key8", Key::Key8),
+    ("Key9", Key::Key9),
+    ("A", Key::A),
+    ("B", Key::B),
+    ("C", Key::C),
+    ("D", Key::D),
+    ("E", Key::E),
+    ("F", Key::F),
+    ("G", Key::G),
+    ("H", Key::H),
+    ("I", Key::I),
+    ("J", Key::J),
+    ("K", Key::K),
+    ("L", Key::L),
+    ("M", Key::M),
+    ("N", Key::N),
+    ("O", Key::O),
+    ("P", Key::P),
+    ("Q", Key::Q),
+    ("R", Key::R),
+    ("S", Key::S),
+    ("T", Key::T),
+    ("U", Key::U),
+    ("V", Key::V),
+    ("W", Key::W),
+    ("X", Key::X),
+    ("Y", Key::Y),
+    ("Z", Key::Z),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Apostrophe", Key::Apostrophe),
+    ("Backquote", Key::Backquote),
+    ("Backslash", Key::Backslash),
+    ("Comma", Key::Comma),
+    ("Equal", Key::Equal),
+    ("LeftBracket", Key::LeftBracket),
+    ("Minus", Key::Minus),
+    ("Period", Key::Period),
+    ("RightBracket", Key::RightBracket),
+    ("Semicolon", Key::Semicolon),
+    ("Slash", Key::Slash),
+    ("Space", Key::Space),
+    ("Enter", Key::Enter),
+    ("Tab", Key::Tab),
+    ("LeftShift", Key::LeftShift),
+    ("RightShift", Key::RightShift),
+    ("LeftCtrl", Key::LeftCtrl),
+    ("RightCtrl", Key::RightCtrl),
+    ("LeftAlt", Key::LeftAlt),
+    ("RightAlt", Key::RightAlt),
+    ("NumPad0", Key::NumPad0),
+    ("NumPad1", Key::NumPad1),
+    ("NumPad2", Key::NumPad2),
+    ("NumPad3", Key::NumPad3),
+    ("NumPad4", Key::NumPad4),
+    ("NumPad5", Key::NumPad5),
+    ("NumPad6", Key::NumPad6),
+    ("NumPad7", Key::NumPad7),
+    ("NumPad8", Key::NumPad8),
+    ("NumPad9", Key::NumPad9),
+    ("NumPadDot", Key::NumPadDot),
+    ("NumPadSlash", Key::NumPadSlash),
+    ("NumPadAsterisk", Key::NumPadAsterisk),
+    ("NumPadMinus", Key::NumPadMinus),
+    ("NumPadPlus", Key::NumPadPlus),
+    ("NumPadEnter", Key::NumPadEnter),
+];
+
+fn key_from_name(name: &str) -> Option<Key> {
+    KEY_NAMES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
+
+// Controller buttons as named by the chip-8-database
+fn button_to_key(button: &str) -> Option<Key> {
+    Some(match button {
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "a" => Key::Space,
+        // Not Shift, which saves to a slot when held with F1-F4
+        "b" => Key::LeftCtrl,
+        _ => return None,
+    })
+}
+
+/// Host keys bound to CHIP-8 keys, any number of which may press the same CHIP-8 key
+pub struct Keymap {
+    bindings: Vec<(Key, u8)>,
+}
+
+impl Default for Keymap {
+    /// The COSMAC VIP hex keypad laid out on the left of a QWERTY keyboard
+    fn default() -> Self {
+        Self::parse(include_str!("../data/keymap.json")).expect("bundled keymap is invalid")
+    }
+}
+
+impl Keymap {
+    /// Keymaps are a JSON object from CHIP-8 keys as hex digits to a host key name, or an array
+    /// of them. CHIP-8 keys left out aren't bound.
+    ///
+    /// Example: `{"5": ["W", "Up"], "8": ["S", "Down"]}`
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let map: Value = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        let map = map
+            .as_object()
+            .ok_or("expected an object from CHIP-8 keys to host keys")?;
+
+        let mut bindings = Vec::new();
+        for (hex, names) in map {
+            let hex = u8::from_str_radix(hex, 16)
+                .ok()
+                .filter(|&hex| hex < 16)
+                .ok_or_else(|| format!("'{hex}' is not a CHIP-8 key, expected 0 to F"))?;
+
+            let names = match names {
+                Value::String(name) => vec![name.as_str()],
+                Value::Array(names) => names
+                    .iter()
+                    .map(|name| name.as_str().ok_or("host key names must be strings"))
+                    .collect::<Result<_, _>>()?,
+                _ => return Err("host keys must be a name or an array of names".to_owned()),
+            };
+            for name in names {
+                let key = key_from_name(name)
+                    .ok_or_else(|| format!("unknown host key '{name}' for CHIP-8 key {hex:X}"))?;
+                bindings.push((key, hex));
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+
+    /// Uses the keymap given on the command line, or else the ROM's own profile next to it
+    /// (`game.keymap` for `game.ch8`) if there is one, or else the default layout
+    pub fn load(path: Option<&Path>, program: &Path) -> Result<Self, String> {
+        let profile = program.with_extension("keymap");
+        let Some(path) = path.or_else(|| profile.is_file().then_some(profile.as_path())) else {
+            return Ok(Self::default());
+        };
+
+        let json = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read keymap from {}: {err}", path.display()))?;
+        let keymap = Self::parse(&json)
+            .map_err(|err| format!("Failed to load keymap from {}: {err}", path.display()))?;
+        info!("Loaded keymap from {}", path.display());
+        Ok(keymap)
+    }
+
+    /// Adds host keys for a ROM's controller buttons, on top of the hex keypad layout
+    pub fn with_buttons(mut self, buttons: &HashMap<String, u8>) -> Self {
+        self.bindings.extend(
+            buttons
+                .iter()
+                .filter_map(|(button, &hex)| Some((button_to_key(button)?, hex))),
+        );
+        self
+    }
+
+    /// Reads which of the 16 CHIP-8 keys are currently held down in the window
+    pub fn read_keys(&self, window: &Window) -> [bool; 16] {
+        let mut keys = [false; 16];
+        for &(key, hex) in &self.bindings {
+            if window.is_key_down(key) {
+                keys[usize::from(hex)] = true;
+            }
+        }
+        keys
+    }
+}
@@ -1,81 +1,81 @@
-use minifb::{Key, Window};
+/// State of the 16 CHIP-8 keys, as last reported by the frontend
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Keypad {
+    keys: [bool; 16],
+    /// Keys held when FX0A last checked, or `None` if it isn't waiting
+    pub(crate) previous: Option<[bool; 16]>,
+    /// Keys pressed since FX0A started waiting, one of which has to be released to end the wait
+    pub(crate) pressed: [bool; 16],
+}
 
-const POSSIBLE_KEYS: [Key; 16] = [
-    Key::Key1,
-    Key::Key2,
-    Key::Key3,
-    Key::Key4,
-    Key::Q,
-    Key::W,
-    Key::E,
-    Key::R,
-    Key::A,
-    Key::S,
-    Key::D,
-    Key::F,
-    Key::Z,
-    Key::X,
-    Key::C,
-    Key::V,
-];
+impl Keypad {
+    pub const fn new() -> Self {
+        Self {
+            keys: [false; 16],
+            previous: None,
+            pressed: [false; 16],
+        }
+    }
 
-fn key_to_hex(key: Key) -> u8 {
-    match key {
-        Key::X => 0x0,
-        Key::Key1 => 0x1,
-        Key::Key2 => 0x2,
-        Key::Key3 => 0x3,
-        Key::Q => 0x4,
-        Key::W => 0x5,
-        Key::E => 0x6,
-        Key::A => 0x7,
-        Key::S => 0x8,
-        Key::D => 0x9,
-        Key::Z => 0xA,
-        Key::C => 0xB,
-        Key::Key4 => 0xC,
-        Key::R => 0xD,
-        Key::F => 0xE,
-        Key::V => 0xF,
-        // Only possible key values that will be converted, so this will never happen
-        _ => unreachable!(),
+    pub fn set_key(&mut self, key_hex: u8, pressed: bool) {
+        if let Some(key) = self.keys.get_mut(key_hex as usize) {
+            *key = pressed;
+        } else {
+            log::error!("invalid hex value for key");
+        }
+    }
+
+    pub const fn set_all(&mut self, keys: [bool; 16]) {
+        self.keys = keys;
     }
-}
 
-fn hex_to_key(hex: u8) -> Option<Key> {
-    Some(match hex {
-        0x0 => Key::X,
-        0x1 => Key::Key1,
-        0x2 => Key::Key2,
-        0x3 => Key::Key3,
-        0x4 => Key::Q,
-        0x5 => Key::W,
-        0x6 => Key::E,
-        0x7 => Key::A,
-        0x8 => Key::S,
-        0x9 => Key::D,
-        0xA => Key::Z,
-        0xB => Key::C,
-        0xC => Key::Key4,
-        0xD => Key::R,
-        0xE => Key::F,
-        0xF => Key::V,
-        _ => {
+    /// Waits for a key to be pressed and released, should be called until it returns the key
+    ///
+    /// Keys already held when the wait starts don't count until they're released and pressed
+    /// again.
+    pub fn take_released_key(&mut self) -> Option<u8> {
+        let previous = self.previous.unwrap_or(self.keys);
+        for ((pressed, &held), was_held) in self.pressed.iter_mut().zip(&self.keys).zip(previous) {
+            *pressed |= held && !was_held;
+        }
+        self.previous = Some(self.keys);
+
+        let key = (0..16).find(|&key| self.pressed[key] && !self.keys[key])?;
+        self.stop_waiting();
+        // Unwrap is ok, key is always below 16
+        Some(key.try_into().unwrap())
+    }
+
+    /// Forgets about the keys pressed during a wait, as the program moved on without it
+    pub const fn stop_waiting(&mut self) {
+        self.previous = None;
+        self.pressed = [false; 16];
+    }
+
+    pub fn get_pressed_key(&self) -> Option<u8> {
+        // Unwrap is ok, position is always below 16
+        self.keys
+            .iter()
+            .position(|&pressed| pressed)
+            .map(|key| key.try_into().unwrap())
+    }
+
+    pub fn is_key_pressed(&self, key_hex: u8) -> Option<bool> {
+        let pressed = self.keys.get(key_hex as usize).copied();
+        if pressed.is_none() {
             log::error!("invalid hex value for key");
-            return None;
         }
-    })
+        pressed
+    }
 }
 
-pub fn get_pressed_key(window: &Window) -> Option<u8> {
-    let pressed_keys = window.get_keys();
-    pressed_keys
-        .into_iter()
-        .find(|key| POSSIBLE_KEYS.contains(key))
-        .map(key_to_hex)
+/// Packs keys into a bitmask with key 0 in the lowest bit, as stored in movies and save states
+pub(crate) fn keys_to_bits(keys: [bool; 16]) -> u16 {
+    keys.iter()
+        .rev()
+        .fold(0, |bits, &held| (bits << 1) | u16::from(held))
 }
 
-pub fn is_key_pressed(window: &Window, key_hex: u8) -> Option<bool> {
-    let key = hex_to_key(key_hex)?;
-    Some(window.is_key_down(key))
+pub(crate) fn keys_from_bits(bits: u16) -> [bool; 16] {
+    std::array::from_fn(|key| bits >> key & 1 == 1)
 }
@@ -0,0 +1,40 @@
+#![warn(clippy::pedantic, clippy::nursery, rust_2018_idioms)]
+#![allow(
+    clippy::must_use_candidate,
+    clippy::missing_panics_doc,
+    clippy::missing_errors_doc
+)]
+
+//! Frontend-agnostic CHIP-8 interpreter core.
+//!
+//! [`Machine`] bundles the CPU state, framebuffer and keypad together. Frontends feed it key
+//! state, call [`Machine::step`]/[`Machine::run_frame`] and present [`Machine::framebuffer`].
+
+pub mod asm;
+pub mod audio;
+pub mod chip8;
+pub mod database;
+pub mod debugger;
+pub mod disasm;
+pub mod display;
+mod fault;
+pub mod instructions;
+pub mod keypad;
+mod machine;
+pub mod movie;
+pub mod octo;
+pub mod platform;
+pub mod quirks;
+pub mod registers;
+pub mod rewind;
+pub mod stack;
+pub mod state;
+
+pub use fault::Fault;
+pub use machine::Machine;
+
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+/// Resolution of the SUPER-CHIP high resolution mode
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
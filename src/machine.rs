@@ -0,0 +1,303 @@
+use log::debug;
+
+use crate::{
+    audio::Sound,
+    chip8::{AccessKind, Chip8, PROGRAM_START},
+    database,
+    display::Framebuffer,
+    fault::Fault,
+    instructions::{DisplayModified, Instruction},
+    keypad::Keypad,
+    platform::Platform,
+    quirks::Quirks,
+    state::{self, StateError},
+};
+
+/// A complete CHIP-8 system: CPU state, screen and keypad
+#[derive(Debug)]
+pub struct Machine {
+    chip8: Chip8,
+    framebuffer: Framebuffer,
+    keypad: Keypad,
+    platform: Platform,
+    quirks: Quirks,
+    /// Set after a draw when the display wait quirk is enabled, cleared on the next timer tick
+    waiting_for_vblank: bool,
+    /// Set by 00FD, after which nothing else is executed
+    exited: bool,
+    /// Address of the most recently executed instruction
+    last_pc: u16,
+    rom_hash: String,
+}
+
+impl Machine {
+    pub fn new(prg: &[u8], platform: Platform) -> Result<Self, Fault> {
+        Ok(Self {
+            chip8: Chip8::load_prg(prg, platform)?,
+            framebuffer: Framebuffer::new(),
+            keypad: Keypad::new(),
+            platform,
+            quirks: Quirks::default(),
+            waiting_for_vblank: false,
+            exited: false,
+            last_pc: PROGRAM_START,
+            rom_hash: database::rom_hash(prg),
+        })
+    }
+
+    /// Fetches, decodes and executes a single instruction
+    ///
+    /// On a fault, the program counter is left pointing at the faulting instruction.
+    /// Does nothing while waiting for a vertical blank, or after the program has exited.
+    pub fn step(&mut self) -> Result<DisplayModified, Fault> {
+        self.chip8.accesses.clear();
+        if self.waiting_for_vblank || self.exited {
+            return Ok(DisplayModified::Unchanged);
+        }
+
+        let pc = self.chip8.pc;
+        let (instruction, size) = self.fetch(pc)?;
+        self.last_pc = pc;
+        self.chip8
+            .record_access(AccessKind::Read, usize::from(pc), usize::from(size));
+
+        self.chip8.pc = pc.wrapping_add(size);
+        let Some(instruction) = instruction else {
+            log::error!("Failed to parse instruction, skipping");
+            return Ok(DisplayModified::Unchanged);
+        };
+        debug!("Parsed instruction: {instruction:?}");
+
+        if !instruction.is_supported_on(self.platform) {
+            log::error!("Instruction not supported on {}, skipping", self.platform);
+            return Ok(DisplayModified::Unchanged);
+        }
+
+        let modified = instruction
+            .execute(
+                &mut self.chip8,
+                &mut self.framebuffer,
+                &mut self.keypad,
+                self.quirks,
+            )
+            .inspect_err(|_| self.chip8.pc = pc)?;
+
+        // Anything else running, such as after the debugger moved the PC, abandons a wait
+        if !matches!(instruction, Instruction::WaitForKey { .. }) {
+            self.keypad.stop_waiting();
+        }
+        match instruction {
+            Instruction::Display { .. } if self.quirks.display_wait => {
+                self.waiting_for_vblank = true;
+            }
+            Instruction::Exit => self.exited = true,
+            _ => {}
+        }
+
+        Ok(modified)
+    }
+
+    /// Reads and decodes the instruction at `addr`, along with its size in bytes
+    ///
+    /// The instruction is `None` if it couldn't be parsed.
+    fn fetch(&self, addr: u16) -> Result<(Option<Instruction>, u16), Fault> {
+        let read = |at: u16| {
+            self.chip8
+                .mem_range(at as usize, 2)
+                // Unwrap is ok, guaranteed to be correct size
+                .map(|bytes| u16::from_be_bytes(bytes.try_into().unwrap()))
+                .map_err(|_| Fault::PcOutOfRange { pc: addr })
+        };
+
+        let instruction = read(addr)?;
+        debug!("Got instruction {instruction:#X}");
+
+        if Instruction::is_long(instruction) {
+            let next = read(addr.wrapping_add(2))?;
+            return Ok((Instruction::parse_long(instruction, next), 4));
+        }
+
+        Ok((Instruction::parse(instruction), 2))
+    }
+
+    /// Executes `instructions` instructions, then ticks the timers once, emulating one 60 Hz frame
+    ///
+    /// Stops early without ticking the timers if an instruction faults.
+    pub fn run_frame(&mut self, instructions: usize) -> Result<DisplayModified, Fault> {
+        let mut modified = DisplayModified::Unchanged;
+
+        for _ in 0..instructions {
+            if self.step()? == DisplayModified::Changed {
+                modified = DisplayModified::Changed;
+            }
+        }
+
+        self.tick_timers();
+
+        Ok(modified)
+    }
+
+    /// Decrements the delay and sound timers, should be called at 60 Hz
+    pub fn tick_timers(&mut self) {
+        self.waiting_for_vblank = false;
+
+        if self.chip8.delay_timer > 0 {
+            self.chip8.delay_timer -= 1;
+            debug!("Decrementing delay timer: {}", self.chip8.delay_timer);
+        }
+
+        if self.chip8.sound_timer > 0 {
+            self.chip8.sound_timer -= 1;
+            debug!("Decrementing sound timer: {}", self.chip8.sound_timer);
+        }
+    }
+
+    /// Replaces the state of all 16 keys, indexed by their hex value
+    pub const fn set_keys(&mut self, keys: [bool; 16]) {
+        self.keypad.set_all(keys);
+    }
+
+    pub fn set_key(&mut self, key_hex: u8, pressed: bool) {
+        self.keypad.set_key(key_hex, pressed);
+    }
+
+    pub const fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    pub const fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub const fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    /// Makes CXNN produce the same numbers on every run with the same seed
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.chip8.rng.seed(seed);
+    }
+
+    /// Seed that reproduces the CXNN numbers from this point on
+    pub fn rng_seed(&self) -> u64 {
+        self.chip8.rng.get_seed()
+    }
+
+    /// SHA-1 of the loaded program, see [`database::rom_hash`]
+    pub fn rom_hash(&self) -> &str {
+        &self.rom_hash
+    }
+
+    pub const fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    pub const fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    /// For debuggers to edit registers and memory
+    pub const fn chip8_mut(&mut self) -> &mut Chip8 {
+        &mut self.chip8
+    }
+
+    /// Address of the instruction that made [`Chip8::accesses`]
+    pub const fn last_pc(&self) -> u16 {
+        self.last_pc
+    }
+
+    /// Whether the program has exited, or is stuck jumping to its own address, which ROMs
+    /// commonly use to halt
+    pub fn is_halted(&self) -> bool {
+        if self.exited {
+            return true;
+        }
+
+        let pc = self.chip8.pc;
+        matches!(self.fetch(pc), Ok((Some(Instruction::Jump { addr }), _)) if addr == pc)
+    }
+
+    /// Whether [`Machine::step`] is doing nothing until the next timer tick, due to the display
+    /// wait quirk
+    pub const fn is_waiting_for_vblank(&self) -> bool {
+        self.waiting_for_vblank
+    }
+
+    /// Decodes the instruction at `addr` without executing it, if it's valid
+    pub fn instruction_at(&self, addr: u16) -> Option<Instruction> {
+        let read = |at: u16| {
+            self.chip8
+                .mem_range(usize::from(at), 2)
+                .ok()
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        };
+
+        let first = read(addr)?;
+        if Instruction::is_long(first) {
+            Instruction::parse_long(first, read(addr.wrapping_add(2))?)
+        } else {
+            Instruction::decode(first)
+        }
+    }
+
+    /// Snapshots everything but the keys held, see [`crate::state`]
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = state::Writer::new(&self.rom_hash);
+        state::write_platform(&mut out, self.platform, self.quirks);
+        state::write_chip8(&mut out, &self.chip8);
+        state::write_framebuffer(&mut out, &self.framebuffer);
+        state::write_keypad(&mut out, &self.keypad);
+        out.bool(self.waiting_for_vblank);
+        out.bool(self.exited);
+        out.u64(self.chip8.rng.get_seed());
+        out.finish()
+    }
+
+    /// Restores a snapshot from [`Machine::save_state`], which must have been saved while
+    /// running the same ROM
+    ///
+    /// The machine is left untouched if the state can't be loaded.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), StateError> {
+        let mut input = state::Reader::new(bytes, &self.rom_hash)?;
+        let (platform, quirks) = state::read_platform(&mut input)?;
+        let mut chip8 = state::read_chip8(&mut input, platform)?;
+        let framebuffer = state::read_framebuffer(&mut input)?;
+        let (previous, pressed) = state::read_keypad(&mut input)?;
+        let waiting_for_vblank = input.bool()?;
+        let exited = input.bool()?;
+        let seed = input.u64()?;
+        input.finish()?;
+
+        chip8.rng.seed(seed);
+        self.last_pc = chip8.pc;
+        self.chip8 = chip8;
+        self.framebuffer = framebuffer;
+        self.keypad.previous = previous;
+        self.keypad.pressed = pressed;
+        self.platform = platform;
+        self.quirks = quirks;
+        self.waiting_for_vblank = waiting_for_vblank;
+        self.exited = exited;
+        Ok(())
+    }
+
+    /// Whether the buzzer should currently be sounding
+    pub const fn sound_active(&self) -> bool {
+        self.chip8.sound_timer > 0
+    }
+
+    /// What should be played until the next timer tick
+    pub const fn sound(&self) -> Sound {
+        if !self.sound_active() {
+            return Sound::Silent;
+        }
+
+        match self.chip8.audio_pattern {
+            Some(pattern) => Sound::Pattern {
+                pattern,
+                pitch: self.chip8.audio_pitch,
+            },
+            None => Sound::Beep,
+        }
+    }
+}
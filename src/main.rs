@@ -1,21 +1,28 @@
 #![warn(clippy::pedantic, clippy::nursery, rust_2018_idioms)]
 
-use std::time::{Duration, Instant};
+use std::path::Path;
 
-use instructions::Instruction;
-use log::{debug, info};
-use minifb::{Window, WindowOptions};
+use chip8::{
+    database::{self, Database},
+    disasm, octo, Machine,
+};
+use log::{error, info};
 
-mod chip8;
+#[cfg(feature = "cpal")]
+mod audio_device;
 mod cli;
-mod display;
-mod instructions;
-mod keypad;
-mod registers;
-mod stack;
-
-pub const WIDTH: usize = 64;
-pub const HEIGHT: usize = 32;
+mod debug;
+mod gdb;
+mod headless;
+mod keymap;
+mod render;
+mod repl;
+mod save_slots;
+mod scheduler;
+mod settings;
+mod sound;
+mod tape;
+mod window;
 
 fn main() {
     simple_logger::SimpleLogger::new()
@@ -24,84 +31,153 @@ fn main() {
         .init()
         .unwrap();
 
-    let args = cli::parse_args().expect("failed to parse arguments");
+    let args = match cli::parse_args().expect("failed to parse arguments") {
+        cli::Command::Run(args) => args,
+        cli::Command::Disasm(disasm) => {
+            let prg = read_program(&disasm.program);
+            print!(
+                "{}",
+                disasm::disassemble(&prg, disasm.platform).listing(disasm.syntax)
+            );
+            return;
+        }
+        cli::Command::Asm(asm) => {
+            let prg = chip8::asm::assemble_file(&asm.source).unwrap_or_else(|err| {
+                error!("{err}");
+                std::process::exit(1);
+            });
+            std::fs::write(&asm.output, &prg).expect("failed to write program");
+            info!("Assembled {} bytes to {}", prg.len(), asm.output.display());
+            return;
+        }
+    };
+
+    let prg = read_program(&args.program);
 
-    let prg = std::fs::read(args.program).expect("failed to open program");
+    let database = args
+        .database
+        .as_ref()
+        .map_or_else(Database::bundled, |path| {
+            let json = std::fs::read_to_string(path).expect("failed to open database");
+            Database::parse(&json).expect("failed to parse database")
+        });
 
     info!("Starting emulator");
 
-    let mut chip8 = chip8::Chip8::load_prg(&prg);
-
-    let mut buf = [0_u32; WIDTH * HEIGHT];
-
-    let mut window = Window::new(
-        "CHIP-8 Emulator",
-        WIDTH,
-        HEIGHT,
-        WindowOptions {
-            resize: false,
-            scale: minifb::Scale::X16,
-            ..Default::default()
-        },
-    )
-    .expect("failed to create window");
-
-    // We do our own limiting
-    window.limit_update_rate(None);
-
-    let mut window_timer = Instant::now();
-    let mut instruction_timer = Instant::now();
-
-    let mut display_modified = instructions::DisplayModified::Unchanged;
-
-    while window.is_open() {
-        // 60 Hz
-        // Update both display and timer
-        if window_timer.elapsed() >= Duration::from_micros(16600) {
-            if chip8.delay_timer > 0 {
-                chip8.delay_timer -= 1;
-                debug!("Decrementing delay timer: {}", chip8.delay_timer);
-            }
+    let rom_hash = database::rom_hash(&prg);
+    let layout =
+        keymap::Keymap::load(args.keymap.as_deref(), &args.program).unwrap_or_else(|err| {
+            error!("{err}");
+            std::process::exit(1);
+        });
+    let settings = settings::resolve(&args, &rom_hash, database.lookup(&rom_hash), layout);
+
+    let mut machine = Machine::new(&prg, settings.platform).unwrap_or_else(|err| {
+        error!("Failed to load program: {err}");
+        std::process::exit(1);
+    });
+    machine.set_quirks(settings.quirks);
+    if let Some(seed) = args.seed {
+        machine.seed_rng(seed);
+    }
+    info!("Random seed is {}", machine.rng_seed());
+    if let Some(path) = &args.load_state {
+        save_slots::load(&mut machine, path).unwrap_or_else(|err| {
+            error!("{err}");
+            std::process::exit(1);
+        });
+    }
+    info!(
+        "Running as {} at {} instructions per frame",
+        settings.platform, settings.tickrate
+    );
+
+    let beeper = sound::beeper(&args);
+    let audio_sinks = sound::open_sinks(&args);
+    let tape = movie_tape(&args, &mut machine);
+    let debugger = debug_frontend(&args, &machine);
+
+    if let Some(headless) = &args.headless {
+        let result = headless::run(
+            machine,
+            headless,
+            settings.tickrate,
+            beeper,
+            audio_sinks,
+            debugger,
+            tape,
+        );
+        // Sinks are dropped by now, so recordings are complete before exiting
+        if result.is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-            if chip8.sound_timer > 0 {
-                // TODO: make it actually play a sound
-                window.set_title("🔔🔔 CHIP-8 Emulator 🔔🔔");
-                chip8.sound_timer -= 1;
-                debug!("Decrementing sound timer: {}", chip8.sound_timer);
-            } else {
-                window.set_title("CHIP-8 Emulator");
-            }
+    let slots = save_slots::Slots::new(&args.program);
+    window::run(
+        machine,
+        &settings,
+        &slots,
+        beeper,
+        audio_sinks,
+        debugger,
+        tape,
+    );
+}
 
-            if matches!(display_modified, instructions::DisplayModified::Changed) {
-                window
-                    .update_with_buffer(&buf, WIDTH, HEIGHT)
-                    .expect("failed to update window");
-            } else {
-                window.update();
+/// Starts recording or playing back a movie, if asked to
+fn movie_tape(args: &cli::Args, machine: &mut Machine) -> Option<tape::Tape> {
+    match (&args.record, &args.play) {
+        (None, None) => None,
+        (Some(path), None) => Some(tape::Tape::record(machine, path.clone())),
+        (None, Some(path)) => {
+            if args.load_state.is_some() {
+                error!(
+                    "--play starts from the movie's own state, it can't be used with --load-state"
+                );
+                std::process::exit(1);
             }
-
-            window_timer = Instant::now();
+            let tape = tape::Tape::play(machine, path).unwrap_or_else(|err| {
+                error!("{err}");
+                std::process::exit(1);
+            });
+            Some(tape)
         }
+        (Some(_), Some(_)) => {
+            error!("--record and --play can't be used together");
+            std::process::exit(1);
+        }
+    }
+}
 
-        let pc = chip8.pc as usize;
-        // Unwrap is ok, guaranteed to be correct size
-        let instruction = u16::from_be_bytes(chip8.mem[pc..pc + 2].try_into().unwrap());
-        debug!("Got instruction 0x{:X}", instruction);
-
-        chip8.pc += 2;
-        let Some(instruction) = Instruction::parse(instruction) else {
-            log::error!("Failed to parse instruction, skipping");
-            continue;
-        };
-        debug!("Parsed instruction: {:?}", instruction);
-
-        display_modified = instruction.execute(&mut chip8, &mut buf, &window, &args.colors);
-
-        // 700 Hz
-        if instruction_timer.elapsed() < Duration::from_micros(1430) {
-            std::thread::sleep(Duration::from_micros(1430) - instruction_timer.elapsed());
+/// Starts the debugger chosen on the command line, if any
+fn debug_frontend(args: &cli::Args, machine: &Machine) -> Option<Box<dyn debug::DebugFrontend>> {
+    match (args.debug, args.gdb) {
+        (false, None) => None,
+        (true, None) => Some(Box::new(repl::Repl::new(machine))),
+        (false, Some(port)) => {
+            let stub = gdb::GdbStub::listen(port).unwrap_or_else(|err| {
+                error!("Failed to start GDB server: {err}");
+                std::process::exit(1);
+            });
+            Some(Box::new(stub))
+        }
+        (true, Some(_)) => {
+            error!("--debug and --gdb can't be used together");
+            std::process::exit(1);
         }
+    }
+}
 
-        instruction_timer = Instant::now();
+/// Reads a ROM, or compiles it first if it's Octo source
+fn read_program(path: &Path) -> Vec<u8> {
+    if path.extension().is_some_and(|ext| ext == "8o") {
+        return octo::compile_file(path).unwrap_or_else(|err| {
+            error!("{err}");
+            std::process::exit(1);
+        });
     }
+
+    std::fs::read(path).expect("failed to open program")
 }
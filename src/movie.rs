@@ -0,0 +1,143 @@
+//! Input movies, replaying a recorded run bit for bit
+//!
+//! A movie starts from a save state, which carries the platform, quirks and RNG seed, followed
+//! by the keys held and the number of instructions executed during every frame. Recording the
+//! instruction counts keeps playback exact even when a frontend's timing varies from frame to
+//! frame.
+//!
+//! The format is a magic number and the format version, the save state prefixed with its length
+//! as a `u32`, then the number of frames as a `u32` followed by the frames themselves: the keys
+//! as a `u16` bitmask with key 0 in the lowest bit, and the instruction count as a `u32`.
+//! Numbers are big-endian.
+
+use std::fmt;
+
+use crate::{keypad, Machine};
+
+/// Bumped whenever the layout changes, so old movies are rejected instead of misread
+pub const VERSION: u16 = 1;
+
+const MAGIC: &[u8; 4] = b"C8MV";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MovieError {
+    NotAMovie,
+    UnsupportedVersion(u16),
+    Malformed(&'static str),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAMovie => write!(f, "not a movie"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "movie version {version} is not supported, expected version {VERSION}"
+            ),
+            Self::Malformed(reason) => write!(f, "malformed movie: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+/// Input during one 60 Hz frame, ending with a timer tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    /// All 16 keys, indexed by their hex value
+    pub keys: [bool; 16],
+    /// Calls to [`Machine::step`] before the timers were ticked
+    pub instructions: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Movie {
+    start: Vec<u8>,
+    frames: Vec<Frame>,
+}
+
+impl Movie {
+    /// Starts recording from the machine's current state
+    pub fn record(machine: &Machine) -> Self {
+        Self {
+            start: machine.save_state(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// State to restore with [`Machine::load_state`] before playing the frames
+    pub fn start_state(&self) -> &[u8] {
+        &self.start
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Unwraps are ok, nothing recorded comes close to 4 GiB
+        let mut out = MAGIC.to_vec();
+        out.extend(VERSION.to_be_bytes());
+        out.extend(u32::try_from(self.start.len()).unwrap().to_be_bytes());
+        out.extend(&self.start);
+        out.extend(u32::try_from(self.frames.len()).unwrap().to_be_bytes());
+        for frame in &self.frames {
+            out.extend(keypad::keys_to_bits(frame.keys).to_be_bytes());
+            out.extend(frame.instructions.to_be_bytes());
+        }
+        out
+    }
+
+    /// The start state is only checked once it's loaded
+    pub fn parse(bytes: &[u8]) -> Result<Self, MovieError> {
+        let mut input = bytes.strip_prefix(MAGIC).ok_or(MovieError::NotAMovie)?;
+
+        let version = u16::from_be_bytes(take(&mut input)?);
+        if version != VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+
+        let len = take_len(&mut input)?;
+        let start = input
+            .get(..len)
+            .ok_or(MovieError::Malformed("unexpected end of movie"))?
+            .to_vec();
+        input = &input[len..];
+
+        let count = take_len(&mut input)?;
+        let mut frames = Vec::with_capacity(count.min(input.len() / 6));
+        for _ in 0..count {
+            let mask = u16::from_be_bytes(take(&mut input)?);
+            let instructions = u32::from_be_bytes(take(&mut input)?);
+            frames.push(Frame {
+                keys: keypad::keys_from_bits(mask),
+                instructions,
+            });
+        }
+
+        if !input.is_empty() {
+            return Err(MovieError::Malformed(
+                "unexpected data after the last frame",
+            ));
+        }
+
+        Ok(Self { start, frames })
+    }
+}
+
+fn take<const N: usize>(input: &mut &[u8]) -> Result<[u8; N], MovieError> {
+    let (taken, rest) = input
+        .split_first_chunk()
+        .ok_or(MovieError::Malformed("unexpected end of movie"))?;
+    *input = rest;
+    Ok(*taken)
+}
+
+fn take_len(input: &mut &[u8]) -> Result<usize, MovieError> {
+    usize::try_from(u32::from_be_bytes(take(input)?))
+        .map_err(|_| MovieError::Malformed("data too long"))
+}
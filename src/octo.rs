@@ -0,0 +1,870 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{chip8::PROGRAM_START, instructions::Instruction, registers::Register};
+
+/// Stops macros which invoke themselves from expanding forever
+const MAX_EXPANSIONS: usize = 10_000;
+
+#[derive(Debug)]
+pub struct OctoError {
+    pub file: PathBuf,
+    /// Starts at 1
+    pub line: usize,
+    /// Starts at 1
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for OctoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+impl Error for OctoError {}
+
+/// Compiles an Octo program into bytes loaded at [`PROGRAM_START`]
+///
+/// Like Octo, the first instruction is a jump to the `main` label.
+pub fn compile(source: &str) -> Result<Vec<u8>, OctoError> {
+    compile_in(source, Path::new("<source>"))
+}
+
+/// Same as [`compile`], reading the source from a file
+pub fn compile_file(path: &Path) -> Result<Vec<u8>, OctoError> {
+    let source = std::fs::read_to_string(path).map_err(|err| OctoError {
+        file: path.to_path_buf(),
+        line: 0,
+        column: 0,
+        message: err.to_string(),
+    })?;
+    compile_in(&source, path)
+}
+
+// Errors are raised without knowing the file, which is filled in here
+fn compile_in(source: &str, file: &Path) -> Result<Vec<u8>, OctoError> {
+    let mut compiler = Compiler {
+        tokens: tokenize(source),
+        ..Compiler::default()
+    };
+    compiler.run().map_err(|err| OctoError {
+        file: file.to_path_buf(),
+        ..err
+    })?;
+    Ok(compiler.rom)
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, message: impl Into<String>) -> OctoError {
+        OctoError {
+            file: PathBuf::new(),
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+
+    for (idx, line) in source.lines().enumerate() {
+        // Comments run to the end of the line
+        let line = line.split('#').next().unwrap_or_default();
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let mut end = start + c.len_utf8();
+            while let Some(&(pos, c)) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                end = pos + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token {
+                text: line[start..end].to_string(),
+                line: idx + 1,
+                column: line[..start].chars().count() + 1,
+            });
+        }
+    }
+
+    // Reversed, so the next token can be popped off the end
+    tokens.reverse();
+    tokens
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+/// An address which isn't known until a label is defined later on
+struct Fixup {
+    /// Offset of the instruction in the ROM
+    offset: usize,
+    /// Whether the address takes up the whole second half of a long instruction, rather than
+    /// the last 12 bits of a regular one
+    long: bool,
+    token: Token,
+}
+
+/// Open `loop`, `if ... begin` or `else` blocks, with where to patch in their jumps
+enum Block {
+    Loop {
+        start: u16,
+        /// Jumps out of the loop from `while`
+        exits: Vec<usize>,
+        token: Token,
+    },
+    Begin {
+        /// Jump to the `else` or `end`, taken when the condition is false
+        jump: usize,
+        token: Token,
+    },
+    Else {
+        /// Jump to the `end`, taken at the end of the true branch
+        jump: usize,
+        token: Token,
+    },
+}
+
+#[derive(Default)]
+struct Compiler {
+    /// Remaining tokens, in reverse
+    tokens: Vec<Token>,
+    rom: Vec<u8>,
+    /// Offset into `rom` where the next byte goes
+    here: usize,
+    /// Labels, `:const` and `:calc` values
+    symbols: HashMap<String, i64>,
+    aliases: HashMap<String, Register>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+    expansions: usize,
+    /// The last token taken, for errors at the end of the source
+    last: Option<Token>,
+}
+
+impl Compiler {
+    fn run(&mut self) -> Result<(), OctoError> {
+        // Space for the jump to main
+        self.emit(Instruction::Jump { addr: 0 });
+
+        while !self.tokens.is_empty() {
+            self.statement()?;
+        }
+
+        if let Some(block) = self.blocks.pop() {
+            let (Block::Loop { token, .. }
+            | Block::Begin { token, .. }
+            | Block::Else { token, .. }) = block;
+            return Err(token.error(format!("'{}' is never closed", token.text)));
+        }
+
+        let main = self.symbols.get("main").copied().ok_or_else(|| OctoError {
+            file: PathBuf::new(),
+            line: 1,
+            column: 1,
+            message: "program has no 'main' label".into(),
+        })?;
+        let main = u16::try_from(main).unwrap_or_default();
+        self.patch(0, false, main);
+
+        for fixup in std::mem::take(&mut self.fixups) {
+            let addr = self
+                .symbols
+                .get(&fixup.token.text)
+                .copied()
+                .ok_or_else(|| {
+                    fixup
+                        .token
+                        .error(format!("undefined name '{}'", fixup.token.text))
+                })?;
+            let max = if fixup.long { 0xFFFF } else { 0xFFF };
+            let addr = u16::try_from(addr)
+                .ok()
+                .filter(|addr| *addr <= max)
+                .ok_or_else(|| {
+                    fixup
+                        .token
+                        .error(format!("address {addr:#X} is out of range"))
+                })?;
+            self.patch(fixup.offset, fixup.long, addr);
+        }
+
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<Token, OctoError> {
+        let token = self.tokens.pop().ok_or_else(|| {
+            let (line, column) = self.last.as_ref().map_or((1, 1), |last| {
+                (last.line, last.column + last.text.chars().count())
+            });
+            OctoError {
+                file: PathBuf::new(),
+                line,
+                column,
+                message: "unexpected end of source".into(),
+            }
+        })?;
+        self.last = Some(token.clone());
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.last().map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, OctoError> {
+        let token = self.next()?;
+        if token.text == text {
+            Ok(token)
+        } else {
+            Err(token.error(format!("expected '{text}', found '{}'", token.text)))
+        }
+    }
+
+    /// Address of the next byte
+    fn addr(&self) -> u16 {
+        PROGRAM_START.wrapping_add(u16::try_from(self.here).unwrap_or(u16::MAX))
+    }
+
+    fn emit_bytes(&mut self, bytes: &[u8]) {
+        let end = self.here + bytes.len();
+        if self.rom.len() < end {
+            self.rom.resize(end, 0);
+        }
+        self.rom[self.here..end].copy_from_slice(bytes);
+        self.here = end;
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        self.emit_bytes(&instruction.encode());
+    }
+
+    /// Fills in the address of the instruction at `offset`
+    fn patch(&mut self, offset: usize, long: bool, addr: u16) {
+        if long {
+            self.rom[offset + 2..offset + 4].copy_from_slice(&addr.to_be_bytes());
+        } else {
+            let [high, low] = (addr & 0xFFF).to_be_bytes();
+            self.rom[offset] = (self.rom[offset] & 0xF0) | high;
+            self.rom[offset + 1] = low;
+        }
+    }
+
+    fn statement(&mut self) -> Result<(), OctoError> {
+        let token = self.next()?;
+
+        // Bare numbers are data, such as sprites
+        if literal(&token.text).is_some() {
+            let byte = self.byte(&token)?;
+            self.emit_bytes(&[byte]);
+            return Ok(());
+        }
+        if token.text.starts_with(':') && token.text.len() > 1 {
+            return self.directive(&token);
+        }
+        if let Some(reg) = self.register(&token.text) {
+            return self.assignment(reg);
+        }
+
+        match token.text.as_str() {
+            ":" => {
+                let name = self.next()?;
+                let addr = i64::from(self.addr());
+                self.define(&name, addr)?;
+            }
+            "i" => self.index()?,
+            "if" => self.conditional()?,
+            "loop" | "again" | "while" | "else" | "end" => self.block(token)?,
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let inreg = self.next_register()?;
+                self.emit(match token.text.as_str() {
+                    "delay" => Instruction::SetDelayTimer { inreg },
+                    "buzzer" => Instruction::SetSoundTimer { inreg },
+                    _ => Instruction::SetPitch { inreg },
+                });
+            }
+            "jump" => self.jump(false)?,
+            "jump0" => self.jump(true)?,
+            _ => self.simple(&token)?,
+        }
+
+        Ok(())
+    }
+
+    /// Statements which don't need any special parsing
+    fn simple(&mut self, token: &Token) -> Result<(), OctoError> {
+        let instruction = match token.text.as_str() {
+            "clear" => Instruction::ClearDisplay,
+            "return" | ";" => Instruction::ReturnSubroutine,
+            "exit" => Instruction::Exit,
+            "lores" => Instruction::LowRes,
+            "hires" => Instruction::HighRes,
+            "scroll-left" => Instruction::ScrollLeft,
+            "scroll-right" => Instruction::ScrollRight,
+            "audio" => Instruction::LoadAudioPattern,
+            "scroll-down" => Instruction::ScrollDown {
+                amount: self.next_nibble()?,
+            },
+            "scroll-up" => Instruction::ScrollUp {
+                amount: self.next_nibble()?,
+            },
+            "plane" => Instruction::SelectPlanes {
+                planes: self.next_nibble()?,
+            },
+            "sprite" => Instruction::Display {
+                xreg: self.next_register()?,
+                yreg: self.next_register()?,
+                height: self.next_nibble()?,
+            },
+            "bcd" => Instruction::BinToDec {
+                inreg: self.next_register()?,
+            },
+            "saveflags" => Instruction::StoreFlags {
+                inreg_max: self.next_register()?,
+            },
+            "loadflags" => Instruction::LoadFlags {
+                outreg_max: self.next_register()?,
+            },
+            "save" | "load" => self.save_load(token)?,
+            name => {
+                if self.macros.contains_key(name) {
+                    return self.expand(token);
+                }
+                // Anything else is a call to a label, which might be defined later
+                let addr = self.target(token, false)?;
+                Instruction::CallSubroutine { addr }
+            }
+        };
+
+        self.emit(instruction);
+        Ok(())
+    }
+
+    fn save_load(&mut self, token: &Token) -> Result<Instruction, OctoError> {
+        let reg1 = self.next_register()?;
+        let save = token.text == "save";
+
+        if self.peek() == Some("-") {
+            self.next()?;
+            let reg2 = self.next_register()?;
+            return Ok(if save {
+                Instruction::StoreRange { reg1, reg2 }
+            } else {
+                Instruction::LoadRange { reg1, reg2 }
+            });
+        }
+
+        Ok(if save {
+            Instruction::StoreMem { inreg_max: reg1 }
+        } else {
+            Instruction::LoadMem { outreg_max: reg1 }
+        })
+    }
+
+    fn jump(&mut self, offset: bool) -> Result<(), OctoError> {
+        let token = self.next()?;
+        let addr = self.target(&token, false)?;
+        self.emit(if offset {
+            Instruction::JumpOffset { addr }
+        } else {
+            Instruction::Jump { addr }
+        });
+        Ok(())
+    }
+
+    /// `vX` followed by an operator
+    fn assignment(&mut self, reg: Register) -> Result<(), OctoError> {
+        let op = self.next()?;
+        let rhs = self.next()?;
+
+        if let Some(reg2) = self.register(&rhs.text) {
+            let (reg1, reg2) = (reg, reg2);
+            self.emit(match op.text.as_str() {
+                ":=" => Instruction::SetReg { reg1, reg2 },
+                "+=" => Instruction::AddReg { reg1, reg2 },
+                "-=" => Instruction::Sub1 { reg1, reg2 },
+                "=-" => Instruction::Sub2 { reg1, reg2 },
+                "|=" => Instruction::Or { reg1, reg2 },
+                "&=" => Instruction::And { reg1, reg2 },
+                "^=" => Instruction::Xor { reg1, reg2 },
+                ">>=" => Instruction::Shr { reg1, reg2 },
+                "<<=" => Instruction::Shl { reg1, reg2 },
+                _ => return Err(op.error(format!("unknown operator '{}'", op.text))),
+            });
+            return Ok(());
+        }
+
+        let instruction = match (op.text.as_str(), rhs.text.as_str()) {
+            (":=", "delay") => Instruction::GetDelayTimer { outreg: reg },
+            (":=", "key") => Instruction::WaitForKey { keyreg: reg },
+            (":=", "random") => Instruction::Rand {
+                outreg: reg,
+                val: self.next_byte()?,
+            },
+            (":=", _) => Instruction::Set {
+                reg,
+                val: self.byte(&rhs)?,
+            },
+            ("+=", _) => Instruction::Add {
+                reg,
+                val: self.byte(&rhs)?,
+            },
+            ("-=", _) => Instruction::Add {
+                reg,
+                val: self.byte(&rhs)?.wrapping_neg(),
+            },
+            _ => return Err(op.error(format!("unknown operator '{}'", op.text))),
+        };
+        self.emit(instruction);
+        Ok(())
+    }
+
+    /// `i` followed by an operator
+    fn index(&mut self) -> Result<(), OctoError> {
+        let op = self.next()?;
+        let rhs = self.next()?;
+
+        let instruction = match (op.text.as_str(), rhs.text.as_str()) {
+            ("+=", _) => Instruction::AddToIndex {
+                inreg: self
+                    .register(&rhs.text)
+                    .ok_or_else(|| rhs.error("expected a register"))?,
+            },
+            (":=", "hex") => Instruction::GetFontChar {
+                inreg: self.next_register()?,
+            },
+            (":=", "bighex") => Instruction::GetBigFontChar {
+                inreg: self.next_register()?,
+            },
+            (":=", "long") => {
+                let token = self.next()?;
+                Instruction::LongSetIndex {
+                    val: self.target(&token, true)?,
+                }
+            }
+            (":=", _) => Instruction::SetIndex {
+                val: self.target(&rhs, false)?,
+            },
+            _ => return Err(op.error(format!("unknown operator '{}'", op.text))),
+        };
+        self.emit(instruction);
+        Ok(())
+    }
+
+    /// Parses a condition, returning the instructions which skip when it's false and when it's
+    /// true
+    fn condition(&mut self) -> Result<(Instruction, Instruction), OctoError> {
+        let reg = self.next_register()?;
+        let op = self.next()?;
+
+        Ok(match op.text.as_str() {
+            "key" => (
+                Instruction::SkipIfNotKey { keyreg: reg },
+                Instruction::SkipIfKey { keyreg: reg },
+            ),
+            "-key" => (
+                Instruction::SkipIfKey { keyreg: reg },
+                Instruction::SkipIfNotKey { keyreg: reg },
+            ),
+            "==" | "!=" => {
+                let rhs = self.next()?;
+                let (eq, ne) = if let Some(reg2) = self.register(&rhs.text) {
+                    (
+                        Instruction::SkipEqReg { reg1: reg, reg2 },
+                        Instruction::SkipNeReg { reg1: reg, reg2 },
+                    )
+                } else {
+                    let num = self.byte(&rhs)?;
+                    (
+                        Instruction::SkipEq { reg, num },
+                        Instruction::SkipNe { reg, num },
+                    )
+                };
+                if op.text == "==" {
+                    (ne, eq)
+                } else {
+                    (eq, ne)
+                }
+            }
+            _ => return Err(op.error(format!("unknown comparison '{}'", op.text))),
+        })
+    }
+
+    fn conditional(&mut self) -> Result<(), OctoError> {
+        let (skip_if_false, skip_if_true) = self.condition()?;
+        let keyword = self.next()?;
+
+        match keyword.text.as_str() {
+            // The next statement is skipped when the condition is false
+            "then" => self.emit(skip_if_false),
+            // A jump to the `else` or `end` is skipped when the condition is true
+            "begin" => {
+                self.emit(skip_if_true);
+                let jump = self.here;
+                self.emit(Instruction::Jump { addr: 0 });
+                self.blocks.push(Block::Begin {
+                    jump,
+                    token: keyword,
+                });
+            }
+            _ => {
+                return Err(keyword.error(format!(
+                    "expected 'then' or 'begin', found '{}'",
+                    keyword.text
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, token: Token) -> Result<(), OctoError> {
+        match token.text.as_str() {
+            "loop" => self.blocks.push(Block::Loop {
+                start: self.addr(),
+                exits: Vec::new(),
+                token,
+            }),
+            "while" => {
+                let (_, skip_if_true) = self.condition()?;
+                self.emit(skip_if_true);
+                let exit = self.here;
+                self.emit(Instruction::Jump { addr: 0 });
+                match self.blocks.last_mut() {
+                    Some(Block::Loop { exits, .. }) => exits.push(exit),
+                    _ => return Err(token.error("'while' outside of a loop")),
+                }
+            }
+            "again" => {
+                let Some(Block::Loop { start, exits, .. }) = self.blocks.pop() else {
+                    return Err(token.error("'again' without a 'loop'"));
+                };
+                self.emit(Instruction::Jump { addr: start });
+                let end = self.addr();
+                for exit in exits {
+                    self.patch(exit, false, end);
+                }
+            }
+            "else" => {
+                let Some(Block::Begin { jump, .. }) = self.blocks.pop() else {
+                    return Err(token.error("'else' without 'if ... begin'"));
+                };
+                let skip_else = self.here;
+                self.emit(Instruction::Jump { addr: 0 });
+                self.patch(jump, false, self.addr());
+                self.blocks.push(Block::Else {
+                    jump: skip_else,
+                    token,
+                });
+            }
+            _ => match self.blocks.pop() {
+                Some(Block::Begin { jump, .. } | Block::Else { jump, .. }) => {
+                    self.patch(jump, false, self.addr());
+                }
+                _ => return Err(token.error("'end' without 'if ... begin'")),
+            },
+        }
+        Ok(())
+    }
+
+    fn directive(&mut self, token: &Token) -> Result<(), OctoError> {
+        match token.text.as_str() {
+            ":alias" => {
+                let name = self.next()?;
+                let reg = self.next_register()?;
+                check_name(&name)?;
+                self.aliases.insert(name.text, reg);
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.next()?;
+                let value = self.value(&value)?;
+                self.define(&name, value)?;
+            }
+            ":calc" => {
+                let name = self.next()?;
+                let open = self.expect("{")?;
+                let tokens = self.braced(&open)?;
+                let mut pos = 0;
+                let value = self.calc(&tokens, &mut pos, &open)?;
+                if let Some(extra) = tokens.get(pos) {
+                    return Err(extra.error(format!("unexpected '{}'", extra.text)));
+                }
+                self.define(&name, value)?;
+            }
+            ":macro" => {
+                let name = self.next()?;
+                check_name(&name)?;
+                let mut params = Vec::new();
+                let open = loop {
+                    let param = self.next()?;
+                    if param.text == "{" {
+                        break param;
+                    }
+                    params.push(param.text);
+                };
+                let body = self.braced(&open)?;
+                self.macros.insert(name.text, Macro { params, body });
+            }
+            ":byte" => {
+                let value = self.next()?;
+                let byte = self.byte(&value)?;
+                self.emit_bytes(&[byte]);
+            }
+            ":org" => {
+                let value = self.next()?;
+                let addr = self.value(&value)?;
+                self.here = usize::try_from(addr - i64::from(PROGRAM_START))
+                    .map_err(|_| value.error("can't place code before the program start"))?;
+            }
+            ":call" => {
+                let target = self.next()?;
+                let addr = self.target(&target, false)?;
+                self.emit(Instruction::CallSubroutine { addr });
+            }
+            _ => return Err(token.error(format!("unknown directive '{}'", token.text))),
+        }
+        Ok(())
+    }
+
+    /// Takes the tokens up to the `}` matching the already taken `open`
+    fn braced(&mut self, open: &Token) -> Result<Vec<Token>, OctoError> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            let token = self.next().map_err(|_| open.error("'{' is never closed"))?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" if depth == 0 => return Ok(tokens),
+                "}" => depth -= 1,
+                _ => {}
+            }
+            tokens.push(token);
+        }
+    }
+
+    fn expand(&mut self, name: &Token) -> Result<(), OctoError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(name.error("too many macro expansions"));
+        }
+
+        let params = self.macros[&name.text].params.clone();
+        let mut args = HashMap::new();
+        for param in params {
+            let arg = self.next()?;
+            args.insert(param, arg.text);
+        }
+
+        let body = self.macros[&name.text]
+            .body
+            .iter()
+            .rev()
+            .map(|token| Token {
+                text: args.get(&token.text).unwrap_or(&token.text).clone(),
+                ..token.clone()
+            });
+        self.tokens.extend(body);
+        Ok(())
+    }
+
+    fn define(&mut self, name: &Token, value: i64) -> Result<(), OctoError> {
+        check_name(name)?;
+        if self.register(&name.text).is_some() {
+            return Err(name.error(format!("'{}' is a register", name.text)));
+        }
+        if self.symbols.insert(name.text.clone(), value).is_some() {
+            return Err(name.error(format!("'{}' is already defined", name.text)));
+        }
+        Ok(())
+    }
+
+    /// Evaluates a `:calc` expression, which like Octo goes from right to left with no precedence
+    fn calc(&self, tokens: &[Token], pos: &mut usize, open: &Token) -> Result<i64, OctoError> {
+        let lhs = self.calc_term(tokens, pos, open)?;
+        let Some(op) = tokens.get(*pos).filter(|op| op.text != ")") else {
+            return Ok(lhs);
+        };
+        *pos += 1;
+        let rhs = self.calc(tokens, pos, open)?;
+
+        Ok(match op.text.as_str() {
+            "+" => lhs.wrapping_add(rhs),
+            "-" => lhs.wrapping_sub(rhs),
+            "*" => lhs.wrapping_mul(rhs),
+            "/" | "%" if rhs == 0 => return Err(op.error("division by zero")),
+            "/" => lhs / rhs,
+            "%" => lhs % rhs,
+            "&" => lhs & rhs,
+            "|" => lhs | rhs,
+            "^" => lhs ^ rhs,
+            "<<" => lhs.checked_shl(shift(rhs)).unwrap_or(0),
+            ">>" => lhs.checked_shr(shift(rhs)).unwrap_or(0),
+            "min" => lhs.min(rhs),
+            "max" => lhs.max(rhs),
+            "<" => i64::from(lhs < rhs),
+            ">" => i64::from(lhs > rhs),
+            "<=" => i64::from(lhs <= rhs),
+            ">=" => i64::from(lhs >= rhs),
+            "==" => i64::from(lhs == rhs),
+            "!=" => i64::from(lhs != rhs),
+            _ => return Err(op.error(format!("unknown operator '{}'", op.text))),
+        })
+    }
+
+    fn calc_term(&self, tokens: &[Token], pos: &mut usize, open: &Token) -> Result<i64, OctoError> {
+        let token = tokens
+            .get(*pos)
+            .ok_or_else(|| open.error("expression ends early"))?;
+        *pos += 1;
+
+        match token.text.as_str() {
+            "-" => Ok(self.calc_term(tokens, pos, open)?.wrapping_neg()),
+            "~" => Ok(!self.calc_term(tokens, pos, open)?),
+            "(" => {
+                let value = self.calc(tokens, pos, open)?;
+                match tokens.get(*pos) {
+                    Some(close) if close.text == ")" => {
+                        *pos += 1;
+                        Ok(value)
+                    }
+                    _ => Err(token.error("'(' is never closed")),
+                }
+            }
+            "HERE" => Ok(i64::from(self.addr())),
+            _ => self.value(token),
+        }
+    }
+
+    /// A number, or a constant or label which is already defined
+    fn value(&self, token: &Token) -> Result<i64, OctoError> {
+        literal(&token.text)
+            .or_else(|| self.symbols.get(&token.text).copied())
+            .ok_or_else(|| token.error(format!("undefined name '{}'", token.text)))
+    }
+
+    fn register(&self, text: &str) -> Option<Register> {
+        if let Some(&reg) = self.aliases.get(text) {
+            return Some(reg);
+        }
+        let digit = text.strip_prefix(['v', 'V'])?;
+        if digit.len() != 1 {
+            return None;
+        }
+        Register::from_u16(u16::from_str_radix(digit, 16).ok()?)
+    }
+
+    fn next_register(&mut self) -> Result<Register, OctoError> {
+        let token = self.next()?;
+        self.register(&token.text)
+            .ok_or_else(|| token.error(format!("expected a register, found '{}'", token.text)))
+    }
+
+    /// Negative values are stored as two's complement
+    fn byte(&self, token: &Token) -> Result<u8, OctoError> {
+        let value = self.value(token)?;
+        u8::try_from(value)
+            .or_else(|_| i8::try_from(value).map(i8::cast_unsigned))
+            .map_err(|_| token.error(format!("{value} doesn't fit in a byte")))
+    }
+
+    fn next_byte(&mut self) -> Result<u8, OctoError> {
+        let token = self.next()?;
+        self.byte(&token)
+    }
+
+    fn next_nibble(&mut self) -> Result<u8, OctoError> {
+        let token = self.next()?;
+        let value = self.value(&token)?;
+        u8::try_from(value)
+            .ok()
+            .filter(|value| *value <= 0xF)
+            .ok_or_else(|| token.error(format!("{value} doesn't fit in a nibble")))
+    }
+
+    /// An address for the instruction about to be emitted, which is filled in later if it's a
+    /// label that isn't defined yet
+    fn target(&mut self, token: &Token, long: bool) -> Result<u16, OctoError> {
+        let max = if long { 0xFFFF } else { 0xFFF };
+
+        match self.value(token) {
+            Ok(addr) => u16::try_from(addr)
+                .ok()
+                .filter(|addr| *addr <= max)
+                .ok_or_else(|| token.error(format!("address {addr:#X} is out of range"))),
+            Err(err) if !is_name(&token.text) => Err(err),
+            Err(_) => {
+                self.fixups.push(Fixup {
+                    offset: self.here,
+                    long,
+                    token: token.clone(),
+                });
+                Ok(0)
+            }
+        }
+    }
+}
+
+fn literal(text: &str) -> Option<i64> {
+    let (negative, digits) = text
+        .strip_prefix('-')
+        .map_or((false, text), |digits| (true, digits));
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn shift(amount: i64) -> u32 {
+    u32::try_from(amount).unwrap_or(u32::MAX)
+}
+
+fn is_name(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn check_name(token: &Token) -> Result<(), OctoError> {
+    if is_name(&token.text) {
+        Ok(())
+    } else {
+        Err(token.error(format!("invalid name '{}'", token.text)))
+    }
+}
@@ -0,0 +1,69 @@
+use std::{fmt, str::FromStr};
+
+use crate::quirks::Quirks;
+
+/// The CHIP-8 variant a program was written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Platform {
+    #[default]
+    Chip8,
+    Chip48,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    /// The quirks the platform's original interpreter had
+    pub const fn quirks(self) -> Quirks {
+        match self {
+            Self::Chip8 => Quirks::CHIP8,
+            Self::Chip48 => Quirks::CHIP48,
+            Self::SuperChip => Quirks::SUPER_CHIP,
+            Self::XoChip => Quirks::XO_CHIP,
+        }
+    }
+
+    /// Bytes of addressable memory
+    pub const fn memory_size(self) -> usize {
+        match self {
+            Self::Chip8 | Self::Chip48 | Self::SuperChip => 4096,
+            Self::XoChip => 65536,
+        }
+    }
+
+    /// Converts a platform id used by the community chip-8-database
+    pub fn from_database_id(id: &str) -> Option<Self> {
+        Some(match id {
+            "originalChip8" | "hybridVIP" | "modernChip8" => Self::Chip8,
+            "chip48" => Self::Chip48,
+            "superchip1" | "superchip" => Self::SuperChip,
+            "xochip" => Self::XoChip,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Chip8 => "CHIP-8",
+            Self::Chip48 => "CHIP-48",
+            Self::SuperChip => "SUPER-CHIP",
+            Self::XoChip => "XO-CHIP",
+        })
+    }
+}
+
+impl FromStr for Platform {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chip8" => Ok(Self::Chip8),
+            "chip48" => Ok(Self::Chip48),
+            "schip" => Ok(Self::SuperChip),
+            "xochip" => Ok(Self::XoChip),
+            _ => Err("platform must be one of 'chip8', 'chip48', 'schip' or 'xochip'"),
+        }
+    }
+}
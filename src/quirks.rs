@@ -0,0 +1,99 @@
+use std::str::FromStr;
+
+use crate::platform::Platform;
+
+/// How much FX55/FX65 advance the index register after accessing memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexIncrement {
+    Unchanged,
+    /// CHIP-48 leaves I pointing at the last register accessed
+    X,
+    /// COSMAC VIP leaves I pointing just past the last register accessed
+    XPlusOne,
+}
+
+/// Behaviors that differ between CHIP-8 implementations, and which ROMs may rely on
+// Quirks are independent toggles rather than a state machine
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY6/8XYE shift VX in place, rather than storing the shifted VY in VX
+    pub shift_vx: bool,
+    pub index_increment: IndexIncrement,
+    /// BNNN becomes BXNN, jumping to XNN + VX rather than NNN + V0
+    pub jump_vx: bool,
+    /// 8XY1/8XY2/8XY3 reset VF to 0
+    pub vf_reset: bool,
+    /// DXYN waits for the next 60 Hz vertical blank before execution continues
+    pub display_wait: bool,
+    /// Sprites are cut off at the screen edges, rather than wrapping around to the other side
+    pub clip_sprites: bool,
+    /// FX0A completes as soon as a key is held, rather than once it's released
+    pub wait_key_press: bool,
+    /// In high resolution, DXYN sets VF to the number of sprite rows that collided or were cut
+    /// off at the bottom, rather than to whether any pixel collided
+    pub collision_rows: bool,
+}
+
+impl Quirks {
+    pub const CHIP8: Self = Self {
+        shift_vx: false,
+        index_increment: IndexIncrement::XPlusOne,
+        jump_vx: false,
+        vf_reset: true,
+        display_wait: true,
+        clip_sprites: true,
+        wait_key_press: false,
+        collision_rows: false,
+    };
+
+    pub const CHIP48: Self = Self {
+        shift_vx: true,
+        index_increment: IndexIncrement::X,
+        jump_vx: true,
+        vf_reset: false,
+        display_wait: false,
+        clip_sprites: true,
+        wait_key_press: false,
+        collision_rows: false,
+    };
+
+    pub const SUPER_CHIP: Self = Self {
+        shift_vx: true,
+        index_increment: IndexIncrement::Unchanged,
+        jump_vx: true,
+        vf_reset: false,
+        display_wait: false,
+        clip_sprites: true,
+        wait_key_press: false,
+        collision_rows: true,
+    };
+
+    pub const XO_CHIP: Self = Self {
+        shift_vx: false,
+        index_increment: IndexIncrement::XPlusOne,
+        jump_vx: false,
+        vf_reset: false,
+        display_wait: false,
+        clip_sprites: false,
+        wait_key_press: false,
+        collision_rows: false,
+    };
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::CHIP8
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = &'static str;
+
+    // Presets are named after the platform they come from
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Platform>()
+            .map(Platform::quirks)
+            .map_err(|_| "quirks must be one of 'chip8', 'chip48', 'schip' or 'xochip'")
+    }
+}
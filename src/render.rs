@@ -0,0 +1,17 @@
+use chip8::display::{Framebuffer, PLANE_1, PLANE_2};
+
+use crate::cli;
+
+/// Maps the machine's monochrome framebuffer to window colors, resizing the buffer to match
+pub fn render(framebuffer: &Framebuffer, buf: &mut Vec<u32>, colors: &cli::Colors) {
+    let pixels = framebuffer.pixels();
+    buf.resize(pixels.len(), 0);
+    for (out, pixel) in buf.iter_mut().zip(pixels) {
+        *out = match pixel {
+            0 => colors.background,
+            PLANE_1 => colors.foreground,
+            PLANE_2 => colors.plane2,
+            _ => colors.blend,
+        };
+    }
+}
@@ -0,0 +1,439 @@
+use std::{
+    io::{BufRead, Write},
+    sync::mpsc::{self, Receiver, TryRecvError},
+};
+
+use chip8::{
+    chip8::AccessKind,
+    debugger::{Condition, Debugger, Stop, Watch, Watchpoint},
+    disasm,
+    instructions::{DisplayModified, Syntax},
+    registers::Register,
+    Fault, Machine,
+};
+
+use crate::debug::{DebugFrontend, State};
+
+const HELP: &str = "\
+Addresses are in hex, counts in decimal. An empty line repeats the last command.
+  break [addr]       set a breakpoint, or list everything with no address (b)
+  break <addr> if <condition>
+                     stop at an address only when the condition holds
+  break if <condition>
+                     stop anywhere once the condition holds, e.g. V3 == 0x10 && I > 0x300
+  delete [addr]      remove a breakpoint, or everything with no address (d)
+  watch <addr> [len] stop after memory is written
+  rwatch <addr> [len]
+                     stop after memory is read, including fetching instructions
+  awatch <addr> [len]
+                     stop after memory is read or written
+  unwatch <addr>     remove the watchpoints starting at an address
+  continue           run until the next breakpoint (c)
+  step [count]       execute instructions (s)
+  next               execute an instruction, stepping over calls (n)
+  finish             run until the current subroutine returns (f)
+  regs               show V0-VF, I, PC and the timers (r)
+  stack              show return addresses on the stack
+  mem <addr> [len]   dump memory (x)
+  list [addr] [n]    disassemble around PC or an address (l)
+  quit               exit the emulator (q)";
+
+/// Command-line debugger, reading commands from stdin
+pub struct Repl {
+    debugger: Debugger,
+    commands: Receiver<String>,
+    last_command: String,
+    /// Set when a command changes the display, until the frontend takes it
+    display_modified: DisplayModified,
+}
+
+impl Repl {
+    pub fn new(machine: &Machine) -> Self {
+        let (sender, commands) = mpsc::channel();
+        // Reading stdin blocks, so it gets its own thread
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        println!("Debugger started, type 'help' for commands");
+        print_location(machine);
+        prompt();
+
+        Self {
+            debugger: Debugger::new(),
+            commands,
+            last_command: String::new(),
+            display_modified: DisplayModified::Unchanged,
+        }
+    }
+
+    fn update(&mut self, machine: &mut Machine, block: bool) -> State {
+        loop {
+            if !self.debugger.is_paused() {
+                let Some(stop) = self.debugger.check(machine) else {
+                    return State::Running;
+                };
+                report_stop(stop);
+                print_location(machine);
+                prompt();
+            }
+
+            // Commands that resume have already executed an instruction, which needs checking
+            // before any more run
+            if let Some(state) = self.read_commands(machine, block) {
+                return state;
+            }
+        }
+    }
+
+    /// Handles commands until one resumes the machine, or returns what to do if there are no
+    /// more for now
+    fn read_commands(&mut self, machine: &mut Machine, block: bool) -> Option<State> {
+        while self.debugger.is_paused() {
+            let line = if block {
+                self.commands.recv().map_err(|_| TryRecvError::Disconnected)
+            } else {
+                self.commands.try_recv()
+            };
+            let line = match line {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => return Some(State::Paused),
+                // Stdin was closed, so nothing else can be done
+                Err(TryRecvError::Disconnected) => return Some(State::Quit),
+            };
+
+            let line = if line.trim().is_empty() {
+                self.last_command.clone()
+            } else {
+                line
+            };
+            if self.command(&line, machine) == State::Quit {
+                return Some(State::Quit);
+            }
+            self.last_command = line;
+
+            if self.debugger.is_paused() {
+                prompt();
+            }
+        }
+        None
+    }
+
+    fn command(&mut self, line: &str, machine: &mut Machine) -> State {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args: Vec<&str> = rest.split_whitespace().collect();
+
+        let result = match command {
+            "" => Ok(()),
+            "help" | "h" => {
+                println!("{HELP}");
+                Ok(())
+            }
+            "quit" | "q" => return State::Quit,
+            "break" | "b" => self.set_breakpoint(rest.trim()),
+            "delete" | "d" => self.delete_breakpoint(&args),
+            "watch" => self.watch(&args, Watch::Write),
+            "rwatch" => self.watch(&args, Watch::Read),
+            "awatch" => self.watch(&args, Watch::Access),
+            "unwatch" => self.unwatch(&args),
+            "continue" | "c" => self.run(machine, Debugger::resume),
+            "next" | "n" => self.run(machine, Debugger::step_over),
+            "finish" | "f" => self.run(machine, Debugger::finish),
+            "step" | "s" => self.step(&args, machine),
+            "regs" | "r" => {
+                print_registers(machine);
+                Ok(())
+            }
+            "stack" => {
+                print_stack(machine);
+                Ok(())
+            }
+            "mem" | "x" => dump_memory(&args, machine),
+            "list" | "l" => list(&args, machine),
+            _ => Err(format!(
+                "unknown command '{command}', type 'help' for commands"
+            )),
+        };
+
+        if let Err(err) = result {
+            println!("{err}");
+        }
+        State::Paused
+    }
+
+    fn set_breakpoint(&mut self, rest: &str) -> Result<(), String> {
+        if rest.is_empty() {
+            self.list_breakpoints();
+            return Ok(());
+        }
+        if let Some(condition) = rest.strip_prefix("if ") {
+            let condition: Condition = condition.parse()?;
+            println!("Stopping when {condition}");
+            self.debugger.add_condition(condition);
+            return Ok(());
+        }
+
+        let (addr, condition) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let addr = parse_addr(addr)?;
+        let condition = match condition.trim() {
+            "" => None,
+            condition => {
+                let condition = condition
+                    .strip_prefix("if ")
+                    .ok_or("expected 'if' before the condition")?;
+                Some(condition.parse::<Condition>()?)
+            }
+        };
+
+        let when = condition
+            .as_ref()
+            .map_or_else(String::new, |condition| format!(" when {condition}"));
+        if self.debugger.add_breakpoint(addr, condition) {
+            println!("Breakpoint set at {addr:#05X}{when}");
+        } else {
+            println!("Breakpoint at {addr:#05X} updated to stop{when}");
+        }
+        Ok(())
+    }
+
+    fn list_breakpoints(&self) {
+        for (addr, condition) in self.debugger.breakpoints() {
+            match condition {
+                Some(condition) => println!("Breakpoint at {addr:#05X} if {condition}"),
+                None => println!("Breakpoint at {addr:#05X}"),
+            }
+        }
+        for condition in self.debugger.conditions() {
+            println!("Stop if {condition}");
+        }
+        for watchpoint in self.debugger.watchpoints() {
+            let kind = match watchpoint.watch {
+                Watch::Read => "Read",
+                Watch::Write => "Write",
+                Watch::Access => "Access",
+            };
+            println!(
+                "{kind} watchpoint on {:#05X}..={:#05X}",
+                watchpoint.start, watchpoint.end
+            );
+        }
+    }
+
+    fn delete_breakpoint(&mut self, args: &[&str]) -> Result<(), String> {
+        let Some(addr) = args.first() else {
+            self.debugger.clear();
+            println!("Deleted all breakpoints, conditions and watchpoints");
+            return Ok(());
+        };
+
+        let addr = parse_addr(addr)?;
+        if self.debugger.remove_breakpoint(addr) {
+            println!("Breakpoint at {addr:#05X} removed");
+            Ok(())
+        } else {
+            Err(format!("no breakpoint at {addr:#05X}"))
+        }
+    }
+
+    fn watch(&mut self, args: &[&str], watch: Watch) -> Result<(), String> {
+        let start = parse_addr(args.first().ok_or("missing address")?)?;
+        let len = args
+            .get(1)
+            .map_or(Ok(1), |len| len.parse::<u16>())
+            .ok()
+            .filter(|&len| len > 0)
+            .ok_or("length must be a positive number")?;
+
+        let end = start.saturating_add(len - 1);
+        self.debugger
+            .add_watchpoint(Watchpoint { start, end, watch });
+        println!("Watching {start:#05X}..={end:#05X}");
+        Ok(())
+    }
+
+    fn unwatch(&mut self, args: &[&str]) -> Result<(), String> {
+        let start = parse_addr(args.first().ok_or("missing address")?)?;
+        if self.debugger.remove_watchpoints(start) {
+            println!("Watchpoints at {start:#05X} removed");
+            Ok(())
+        } else {
+            Err(format!("no watchpoints at {start:#05X}"))
+        }
+    }
+
+    /// Starts running with one of the [`Debugger`]'s methods, reporting any fault
+    fn run(
+        &mut self,
+        machine: &mut Machine,
+        start: fn(&mut Debugger, &mut Machine) -> Result<DisplayModified, Fault>,
+    ) -> Result<(), String> {
+        let modified = start(&mut self.debugger, machine);
+        self.note_display(modified.as_ref().ok().copied());
+        match modified {
+            Ok(_) if self.debugger.is_paused() => {
+                print_location(machine);
+                Ok(())
+            }
+            Ok(_) => Ok(()),
+            Err(fault) => Err(format!("Program faulted: {fault}")),
+        }
+    }
+
+    fn step(&mut self, args: &[&str], machine: &mut Machine) -> Result<(), String> {
+        let count = args
+            .first()
+            .map_or(Ok(1), |count| count.parse::<usize>())
+            .map_err(|_| "count must be a number")?;
+
+        for _ in 0..count {
+            let modified = self.debugger.step(machine);
+            self.note_display(modified.as_ref().ok().copied());
+            modified.map_err(|fault| format!("Program faulted: {fault}"))?;
+        }
+        print_location(machine);
+        Ok(())
+    }
+
+    fn note_display(&mut self, modified: Option<DisplayModified>) {
+        if modified == Some(DisplayModified::Changed) {
+            self.display_modified = DisplayModified::Changed;
+        }
+    }
+}
+
+impl DebugFrontend for Repl {
+    fn poll(&mut self, machine: &mut Machine) -> State {
+        self.update(machine, false)
+    }
+
+    fn wait(&mut self, machine: &mut Machine) -> State {
+        self.update(machine, true)
+    }
+
+    fn fault(&mut self, machine: &Machine, fault: &Fault) {
+        self.debugger.pause();
+        println!("Program faulted: {fault}");
+        print_location(machine);
+        prompt();
+    }
+
+    fn take_display_modified(&mut self) -> DisplayModified {
+        std::mem::replace(&mut self.display_modified, DisplayModified::Unchanged)
+    }
+}
+
+fn report_stop(stop: Stop) {
+    match stop {
+        Stop::Breakpoint { addr } => println!("Breakpoint at {addr:#05X}"),
+        Stop::Watchpoint { pc, access } => {
+            let verb = match access.kind {
+                AccessKind::Read => "read",
+                AccessKind::Write => "wrote",
+            };
+            let last = access.addr.saturating_add(access.len.saturating_sub(1));
+            println!(
+                "Watchpoint: instruction at {pc:#05X} {verb} {:#05X}..={last:#05X}",
+                access.addr
+            );
+        }
+        Stop::Condition => println!("Condition met"),
+        Stop::StepOver | Stop::Finish => {}
+    }
+}
+
+fn prompt() {
+    print!("(chip8) ");
+    // Nothing useful to do if stdout is gone
+    let _ = std::io::stdout().flush();
+}
+
+fn parse_addr(s: &str) -> Result<u16, String> {
+    let digits = s.trim_start_matches("0x");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid address '{s}'"))
+}
+
+fn print_location(machine: &Machine) {
+    let pc = machine.chip8().pc;
+    match machine.instruction_at(pc) {
+        Some(instruction) => println!("{pc:#05X}: {instruction}"),
+        None => println!("{pc:#05X}: (invalid instruction)"),
+    }
+}
+
+fn print_registers(machine: &Machine) {
+    let chip8 = machine.chip8();
+    let values: Vec<String> = Register::iter_until(Register::VF)
+        .map(|reg| format!("{reg}={:02X}", chip8.registers[reg]))
+        .collect();
+    for row in values.chunks(8) {
+        println!("{}", row.join(" "));
+    }
+    println!(
+        "I={:03X} PC={:03X} DT={:02X} ST={:02X}",
+        chip8.registers.index, chip8.pc, chip8.delay_timer, chip8.sound_timer
+    );
+}
+
+fn print_stack(machine: &Machine) {
+    let stack = machine.chip8().stack.as_slice();
+    if stack.is_empty() {
+        println!("Stack is empty");
+    }
+    // Most recent call first, like a backtrace
+    for (depth, addr) in stack.iter().rev().enumerate() {
+        println!("#{depth} {addr:#05X}");
+    }
+}
+
+fn dump_memory(args: &[&str], machine: &Machine) -> Result<(), String> {
+    let addr = usize::from(parse_addr(args.first().ok_or("missing address")?)?);
+    let len = args
+        .get(1)
+        .map_or(Ok(64), |len| len.parse::<usize>())
+        .map_err(|_| "length must be a number")?;
+
+    let mem = &machine.chip8().mem;
+    let end = addr.saturating_add(len).min(mem.len());
+    let bytes = mem.get(addr..end).ok_or("address is out of range")?;
+
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{byte:02X}")).collect();
+        println!("{:#05X}: {}", addr + row * 16, hex.join(" "));
+    }
+    Ok(())
+}
+
+fn list(args: &[&str], machine: &Machine) -> Result<(), String> {
+    let pc = machine.chip8().pc;
+    let (start, count) = match args.first() {
+        Some(addr) => (parse_addr(addr)?, 10),
+        // A few instructions before PC for context, assuming they're aligned with it
+        None => (pc.saturating_sub(8), 10),
+    };
+    let count = args
+        .get(1)
+        .map_or(Ok(count), |count| count.parse::<usize>())
+        .map_err(|_| "count must be a number")?;
+
+    let mem = &machine.chip8().mem;
+    let begin = usize::from(start).min(mem.len());
+    // Room for every instruction to be long
+    let end = begin.saturating_add(count.saturating_mul(4)).min(mem.len());
+    let disassembly = disasm::disassemble_at(&mem[begin..end], start, machine.platform());
+
+    for line in disassembly.lines.iter().take(count) {
+        let marker = if line.addr == pc { "=>" } else { "  " };
+        let text = line.instruction.map_or_else(
+            || "(data)".to_owned(),
+            |instruction| instruction.mnemonic(Syntax::Cowgod).to_string(),
+        );
+        println!("{marker} {:#05X}: {text}", line.addr);
+    }
+    Ok(())
+}
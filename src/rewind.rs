@@ -0,0 +1,160 @@
+//! Stepping backwards through recent save states
+//!
+//! Consecutive frames' states are nearly identical, so only the newest is kept in full. Every
+//! older state is stored as a delta against the one after it: runs of bytes that differ, each
+//! prefixed with the number of identical bytes skipped and the run's length.
+
+use std::collections::VecDeque;
+
+/// Delta kinds, in the first byte of a delta
+const RUNS: u8 = 0;
+/// States of different lengths, such as after switching resolution, are stored in full
+const FULL: u8 = 1;
+
+/// A ring buffer of the most recent states from [`Machine::save_state`](crate::Machine::save_state)
+#[derive(Debug)]
+pub struct Rewind {
+    /// The newest state, in full
+    latest: Option<Vec<u8>>,
+    /// Deltas turning each state into the one before it, oldest first
+    deltas: VecDeque<Vec<u8>>,
+    /// Maximum number of states kept, including the newest
+    max_states: usize,
+    /// Maximum size of the newest state and all deltas together
+    max_bytes: usize,
+    bytes: usize,
+}
+
+impl Rewind {
+    /// Oldest states are dropped once there are `max_states` of them, or they take up more than
+    /// `max_bytes`
+    pub const fn new(max_states: usize, max_bytes: usize) -> Self {
+        Self {
+            latest: None,
+            deltas: VecDeque::new(),
+            max_states,
+            max_bytes,
+            bytes: 0,
+        }
+    }
+
+    /// Records a new state, usually once per frame
+    pub fn push(&mut self, state: Vec<u8>) {
+        if self.max_states == 0 {
+            return;
+        }
+
+        if let Some(latest) = self.latest.take() {
+            let delta = diff(&state, &latest);
+            self.bytes = self.bytes - latest.len() + delta.len();
+            self.deltas.push_back(delta);
+        }
+        self.bytes += state.len();
+        self.latest = Some(state);
+
+        while self.deltas.len() >= self.max_states || self.bytes > self.max_bytes {
+            let Some(oldest) = self.deltas.pop_front() else {
+                break;
+            };
+            self.bytes -= oldest.len();
+        }
+    }
+
+    /// Drops the newest state, returning the one before it, which becomes the newest
+    ///
+    /// Returns `None` once the oldest state is reached.
+    pub fn step_back(&mut self) -> Option<&[u8]> {
+        let delta = self.deltas.pop_back()?;
+        // Unwrap is ok, there are only deltas after a state
+        let latest = self.latest.take().unwrap();
+
+        let previous = patch(&latest, &delta);
+        self.bytes = self.bytes - latest.len() - delta.len() + previous.len();
+        Some(self.latest.insert(previous))
+    }
+
+    /// Number of states, including the newest
+    pub fn len(&self) -> usize {
+        self.deltas.len() + usize::from(self.latest.is_some())
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    /// Memory used by the stored states, in bytes
+    pub const fn size(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// Encodes how to turn `from` into `to`
+fn diff(from: &[u8], to: &[u8]) -> Vec<u8> {
+    if from.len() != to.len() {
+        let mut delta = vec![FULL];
+        delta.extend(to);
+        return delta;
+    }
+
+    let mut delta = vec![RUNS];
+    let mut pos = 0;
+    while let Some(start) = (pos..to.len()).find(|&idx| from[idx] != to[idx]) {
+        let end = (start..to.len())
+            .find(|&idx| from[idx] == to[idx])
+            .unwrap_or(to.len());
+        write_varint(&mut delta, start - pos);
+        write_varint(&mut delta, end - start);
+        delta.extend(&to[start..end]);
+        pos = end;
+    }
+    delta
+}
+
+/// Applies a delta from [`diff`] to the state it was made from
+fn patch(from: &[u8], delta: &[u8]) -> Vec<u8> {
+    let (&kind, mut runs) = delta.split_first().expect("empty rewind delta");
+    if kind == FULL {
+        return runs.to_vec();
+    }
+
+    let mut state = from.to_vec();
+    let mut pos = 0;
+    while !runs.is_empty() {
+        pos += read_varint(&mut runs);
+        let len = read_varint(&mut runs);
+        let (bytes, rest) = runs.split_at(len);
+        state[pos..pos + len].copy_from_slice(bytes);
+        runs = rest;
+        pos += len;
+    }
+    state
+}
+
+/// LEB128, so short runs and gaps only take a byte
+fn write_varint(out: &mut Vec<u8>, mut val: usize) {
+    loop {
+        // Truncation is intended, only the low 7 bits are kept
+        #[allow(clippy::cast_possible_truncation)]
+        let byte = (val & 0x7F) as u8;
+        val >>= 7;
+        if val == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_varint(input: &mut &[u8]) -> usize {
+    let mut val = 0;
+    let mut shift = 0;
+    while let Some((&byte, rest)) = input.split_first() {
+        *input = rest;
+        val |= usize::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    val
+}
@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+
+use chip8::Machine;
+use log::{error, info};
+use minifb::{Key, KeyRepeat, Window};
+
+/// Loads the state in the matching slot, or saves to it while shift is held
+const SLOT_KEYS: [Key; 4] = [Key::F1, Key::F2, Key::F3, Key::F4];
+
+/// Save state files kept next to the program, one per hotkey slot
+pub struct Slots {
+    program: PathBuf,
+}
+
+impl Slots {
+    pub fn new(program: &Path) -> Self {
+        Self {
+            program: program.to_owned(),
+        }
+    }
+
+    /// Slot 1 of `game.ch8` is `game.state1`
+    fn path(&self, slot: usize) -> PathBuf {
+        self.program.with_extension(format!("state{slot}"))
+    }
+
+    /// Saves or loads states for any slot hotkeys just pressed
+    ///
+    /// Returns whether a state was loaded, which replaces the display.
+    pub fn handle_hotkeys(&self, window: &Window, machine: &mut Machine) -> bool {
+        let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+        let mut loaded = false;
+
+        for (idx, &key) in SLOT_KEYS.iter().enumerate() {
+            if !window.is_key_pressed(key, KeyRepeat::No) {
+                continue;
+            }
+
+            let path = self.path(idx + 1);
+            if shift {
+                save(machine, &path);
+            } else {
+                match load(machine, &path) {
+                    Ok(()) => loaded = true,
+                    Err(err) => error!("{err}"),
+                }
+            }
+        }
+
+        loaded
+    }
+}
+
+pub fn save(machine: &Machine, path: &Path) {
+    match std::fs::write(path, machine.save_state()) {
+        Ok(()) => info!("Saved state to {}", path.display()),
+        Err(err) => error!("Failed to save state to {}: {err}", path.display()),
+    }
+}
+
+pub fn load(machine: &mut Machine, path: &Path) -> Result<(), String> {
+    let bytes = std::fs::read(path)
+        .map_err(|err| format!("Failed to read state from {}: {err}", path.display()))?;
+    machine
+        .load_state(&bytes)
+        .map_err(|err| format!("Failed to load state from {}: {err}", path.display()))?;
+    info!("Loaded state from {}", path.display());
+    Ok(())
+}
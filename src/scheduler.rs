@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+use log::info;
+
+/// One 60 Hz frame
+pub const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Falling further behind than this skips ahead, rather than running frames back to back to
+/// catch up
+const MAX_LAG: Duration = Duration::from_nanos(4 * 1_000_000_000 / 60);
+
+/// Paces emulation to 60 Hz frames, each of which runs a number of instructions in one go
+///
+/// Frame deadlines advance by exactly one frame at a time, so oversleeping one frame is made up
+/// for in the next instead of drifting.
+pub struct Scheduler {
+    /// Instructions per frame
+    tickrate: usize,
+    /// Time between frames, shorter than [`FRAME`] while fast-forwarding
+    period: Duration,
+    deadline: Instant,
+    /// Counted since `measured_at`, for the achieved speed
+    frames: u32,
+    instructions: u64,
+    measured_at: Instant,
+    /// Instructions and frames per second over the last full second
+    speed: Option<(u64, u32)>,
+}
+
+impl Scheduler {
+    pub fn new(tickrate: usize) -> Self {
+        let now = Instant::now();
+        Self {
+            tickrate: tickrate.max(1),
+            period: FRAME,
+            deadline: now + FRAME,
+            frames: 0,
+            instructions: 0,
+            measured_at: now,
+            speed: None,
+        }
+    }
+
+    pub const fn tickrate(&self) -> usize {
+        self.tickrate
+    }
+
+    /// Raises the tickrate by about 10%
+    pub fn faster(&mut self) {
+        self.tickrate += (self.tickrate / 10).max(1);
+        info!("Running at {} instructions per frame", self.tickrate);
+    }
+
+    /// Lowers the tickrate by about 10%, down to a single instruction per frame
+    pub fn slower(&mut self) {
+        self.tickrate = self
+            .tickrate
+            .saturating_sub((self.tickrate / 10).max(1))
+            .max(1);
+        info!("Running at {} instructions per frame", self.tickrate);
+    }
+
+    /// Runs `multiplier` frames in the time of one, or at normal speed for 1
+    pub fn set_multiplier(&mut self, multiplier: u32) {
+        self.period = FRAME / multiplier.max(1);
+    }
+
+    pub fn is_fast_forwarding(&self) -> bool {
+        self.period < FRAME
+    }
+
+    pub const fn count_instruction(&mut self) {
+        self.instructions += 1;
+    }
+
+    /// Sleeps until the current frame is due to end
+    pub fn wait_for_frame(&mut self) {
+        let now = Instant::now();
+        if let Some(remaining) = self.deadline.checked_duration_since(now) {
+            std::thread::sleep(remaining);
+        } else if now - self.deadline > MAX_LAG {
+            self.deadline = now;
+        }
+        self.deadline += self.period;
+
+        self.frames += 1;
+        let elapsed = self.measured_at.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            let per_second = |count: f64| (count / elapsed.as_secs_f64()).round();
+            // Rounding is fine, these are nowhere near the limits
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                clippy::cast_precision_loss
+            )]
+            let speed = (
+                per_second(self.instructions as f64) as u64,
+                per_second(f64::from(self.frames)) as u32,
+            );
+            self.speed = Some(speed);
+            self.frames = 0;
+            self.instructions = 0;
+            self.measured_at = Instant::now();
+        }
+    }
+
+    /// Starts timing from scratch, after emulation was paused
+    pub fn reset(&mut self) {
+        *self = Self {
+            period: self.period,
+            ..Self::new(self.tickrate)
+        };
+    }
+
+    /// Achieved instructions and frames per second, once measured
+    pub const fn speed(&self) -> Option<(u64, u32)> {
+        self.speed
+    }
+}
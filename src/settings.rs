@@ -0,0 +1,85 @@
+use chip8::{database::RomInfo, platform::Platform, quirks::Quirks};
+use log::info;
+
+use crate::{cli, keymap::Keymap};
+
+// Roughly 700 Hz
+pub const DEFAULT_TICKRATE: usize = 12;
+
+/// Emulator settings after combining CLI arguments, ROM database entries and defaults
+pub struct Settings {
+    pub platform: Platform,
+    pub quirks: Quirks,
+    /// Instructions per 60 Hz frame
+    pub tickrate: usize,
+    pub colors: cli::Colors,
+    /// Host keys for the hex keypad, and for the ROM's controller buttons
+    pub keymap: Keymap,
+    pub rewind: cli::Rewind,
+}
+
+// CLI arguments take priority over what was detected
+pub fn resolve(
+    args: &cli::Args,
+    rom_hash: &str,
+    detected: Option<&RomInfo>,
+    layout: Keymap,
+) -> Settings {
+    if let Some(rom) = detected {
+        info!(
+            "Detected \"{}\" for {} (tickrate {})",
+            rom.title,
+            rom.platform,
+            rom.tickrate
+                .map_or_else(|| "unknown".to_owned(), |tickrate| tickrate.to_string())
+        );
+    } else {
+        info!("ROM with SHA-1 {rom_hash} not found in database, using defaults");
+    }
+
+    let platform = args
+        .platform
+        .or_else(|| detected.map(|rom| rom.platform))
+        .unwrap_or_default();
+
+    // An explicit platform means the detected quirks were for something else
+    let detected_quirks = detected
+        .filter(|_| args.platform.is_none())
+        .map(|rom| rom.quirks);
+    let quirks = args
+        .quirks
+        .or(detected_quirks)
+        .unwrap_or_else(|| platform.quirks());
+
+    Settings {
+        platform,
+        quirks,
+        tickrate: args
+            .tickrate
+            .or_else(|| detected.and_then(|rom| rom.tickrate))
+            .unwrap_or(DEFAULT_TICKRATE),
+        colors: cli::Colors {
+            foreground: args
+                .foreground
+                .or_else(|| detected.and_then(|rom| rom.foreground))
+                .unwrap_or(0xFF_FF_FF),
+            background: args
+                .background
+                .or_else(|| detected.and_then(|rom| rom.background))
+                .unwrap_or(0x00_00_00),
+            plane2: args
+                .plane2
+                .or_else(|| detected.and_then(|rom| rom.plane2))
+                .unwrap_or(0x55_55_55),
+            blend: args
+                .blend
+                .or_else(|| detected.and_then(|rom| rom.blend))
+                .unwrap_or(0xAA_AA_AA),
+        },
+        keymap: match detected {
+            Some(rom) => layout.with_buttons(&rom.keys),
+            None => layout,
+        },
+        rewind: args.rewind,
+    }
+}
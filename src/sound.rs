@@ -0,0 +1,34 @@
+use chip8::audio::{AudioSink, Beeper, WavSink};
+use log::{error, info};
+
+use crate::cli;
+
+/// Opens every audio output requested on the command line
+pub fn open_sinks(args: &cli::Args) -> Vec<Box<dyn AudioSink>> {
+    let mut sinks: Vec<Box<dyn AudioSink>> = Vec::new();
+
+    if let Some(path) = &args.audio.wav {
+        match WavSink::create(path) {
+            Ok(sink) => {
+                info!("Recording audio to {}", path.display());
+                sinks.push(Box::new(sink));
+            }
+            Err(err) => error!("Failed to create WAV file: {err}"),
+        }
+    }
+
+    // There's no one to listen to a headless run
+    #[cfg(feature = "cpal")]
+    if !args.audio.mute && args.headless.is_none() {
+        match crate::audio_device::DeviceSink::open() {
+            Ok(sink) => sinks.push(Box::new(sink)),
+            Err(err) => error!("Failed to open audio device: {err}"),
+        }
+    }
+
+    sinks
+}
+
+pub fn beeper(args: &cli::Args) -> Beeper {
+    Beeper::new(args.audio.pitch, args.audio.volume)
+}
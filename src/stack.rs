@@ -1,3 +1,5 @@
+use crate::fault::Fault;
+
 const STACK_LEN: usize = 16;
 
 #[derive(Debug)]
@@ -7,6 +9,9 @@ pub struct Stack {
 }
 
 impl Stack {
+    /// Maximum number of nested subroutine calls
+    pub const CAPACITY: usize = STACK_LEN;
+
     pub const fn new() -> Self {
         Self {
             data: [0; STACK_LEN],
@@ -14,23 +19,59 @@ impl Stack {
         }
     }
 
-    pub fn push(&mut self, val: u16) {
+    pub const fn push(&mut self, val: u16) -> Result<(), Fault> {
         // Index should range from 0..STACK_LEN (exclusive)
         // Incremented at end of previous run, so if push is attempted and pointer is above max len,
-        // then stack will overflow (should crash here, non-recoverable error)
-        assert!(self.cur_idx < STACK_LEN, "emulated program stack overflow");
+        // then stack will overflow (non-recoverable error for the program)
+        if self.cur_idx >= STACK_LEN {
+            return Err(Fault::StackOverflow);
+        }
         self.data[self.cur_idx] = val;
         self.cur_idx += 1;
+        Ok(())
     }
 
-    pub fn pop(&mut self) -> u16 {
+    pub const fn pop(&mut self) -> Result<u16, Fault> {
         // Make sure stack counter doesn't 'underflow' (wrap around)
-        // Should crash, as program might expect non-existent value
-        self.cur_idx = self
-            .cur_idx
-            .checked_sub(1)
-            .expect("emulated program popped from empty stack");
+        // Should fault, as program might expect non-existent value
+        let Some(idx) = self.cur_idx.checked_sub(1) else {
+            return Err(Fault::StackUnderflow);
+        };
+        self.cur_idx = idx;
         // Old values are not overwritten, but shouldn't cause any problems
-        self.data[self.cur_idx]
+        Ok(self.data[self.cur_idx])
+    }
+
+    /// Return addresses currently on the stack, oldest first
+    pub fn as_slice(&self) -> &[u16] {
+        &self.data[..self.cur_idx]
+    }
+
+    /// Number of return addresses currently on the stack
+    pub const fn len(&self) -> usize {
+        self.cur_idx
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.cur_idx == 0
+    }
+
+    /// Every slot, including stale ones above the pointer, along with the pointer itself
+    pub(crate) const fn raw_parts(&self) -> (&[u16; STACK_LEN], usize) {
+        (&self.data, self.cur_idx)
+    }
+
+    /// Returns `None` if the pointer is out of range
+    pub(crate) const fn from_raw_parts(data: [u16; STACK_LEN], cur_idx: usize) -> Option<Self> {
+        if cur_idx > STACK_LEN {
+            return None;
+        }
+        Some(Self { data, cur_idx })
+    }
+}
+
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,331 @@
+//! Save states, snapshotting everything about a [`Machine`](crate::Machine) but the keys held
+//!
+//! States are a compact binary format: a magic number, the format version and the SHA-1 of the
+//! ROM they were saved from, followed by the platform and quirks, the [`Chip8`] state, the
+//! framebuffer, the keys FX0A is waiting on, the machine's own flags and the RNG seed. Numbers
+//! are big-endian, and variable-length data is prefixed with its length as a `u32`.
+
+use std::fmt;
+
+use crate::{
+    chip8::Chip8,
+    display::Framebuffer,
+    keypad::{keys_from_bits, keys_to_bits, Keypad},
+    platform::Platform,
+    quirks::{IndexIncrement, Quirks},
+    registers::{Register, Registers},
+    stack::Stack,
+};
+
+/// Bumped whenever the layout changes, so old states are rejected instead of misread
+pub const VERSION: u16 = 4;
+
+const MAGIC: &[u8; 4] = b"C8ST";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    NotAState,
+    UnsupportedVersion(u16),
+    /// The state was saved while running a different ROM
+    WrongRom {
+        expected: String,
+        found: String,
+    },
+    Malformed(&'static str),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAState => write!(f, "not a save state"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "save state version {version} is not supported, expected version {VERSION}"
+            ),
+            Self::WrongRom { expected, found } => write!(
+                f,
+                "save state is for the ROM with SHA-1 {found}, but {expected} is running"
+            ),
+            Self::Malformed(reason) => write!(f, "malformed save state: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+pub(crate) struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    /// Starts a state with its header
+    pub(crate) fn new(rom_hash: &str) -> Self {
+        let mut writer = Self {
+            bytes: MAGIC.to_vec(),
+        };
+        writer.u16(VERSION);
+        writer.bytes(rom_hash.as_bytes());
+        writer
+    }
+
+    pub(crate) fn u8(&mut self, val: u8) {
+        self.bytes.push(val);
+    }
+
+    pub(crate) fn u16(&mut self, val: u16) {
+        self.bytes.extend(val.to_be_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, val: u64) {
+        self.bytes.extend(val.to_be_bytes());
+    }
+
+    pub(crate) fn bool(&mut self, val: bool) {
+        self.u8(u8::from(val));
+    }
+
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) {
+        // Unwrap is ok, nothing in a machine comes close to 4 GiB
+        self.bytes
+            .extend(u32::try_from(bytes.len()).unwrap().to_be_bytes());
+        self.bytes.extend(bytes);
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Checks the header, which must match the running ROM
+    pub(crate) fn new(bytes: &'a [u8], rom_hash: &str) -> Result<Self, StateError> {
+        let bytes = bytes.strip_prefix(MAGIC).ok_or(StateError::NotAState)?;
+        let mut reader = Self { bytes };
+
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let found = String::from_utf8_lossy(reader.bytes()?).into_owned();
+        if found != rom_hash {
+            return Err(StateError::WrongRom {
+                expected: rom_hash.to_owned(),
+                found,
+            });
+        }
+
+        Ok(reader)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let (taken, rest) = self
+            .bytes
+            .split_first_chunk()
+            .ok_or(StateError::Malformed("unexpected end of state"))?;
+        self.bytes = rest;
+        Ok(*taken)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(u8::from_be_bytes(self.take()?))
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_be_bytes(self.take()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_be_bytes(self.take()?))
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Malformed("invalid boolean")),
+        }
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], StateError> {
+        let len = usize::try_from(u32::from_be_bytes(self.take()?))
+            .map_err(|_| StateError::Malformed("data too long"))?;
+        if len > self.bytes.len() {
+            return Err(StateError::Malformed("unexpected end of state"));
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    /// Checks that everything was read
+    pub(crate) const fn finish(self) -> Result<(), StateError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(StateError::Malformed(
+                "unexpected data after the end of state",
+            ))
+        }
+    }
+}
+
+pub(crate) fn write_platform(out: &mut Writer, platform: Platform, quirks: Quirks) {
+    out.u8(match platform {
+        Platform::Chip8 => 0,
+        Platform::Chip48 => 1,
+        Platform::SuperChip => 2,
+        Platform::XoChip => 3,
+    });
+    out.bool(quirks.shift_vx);
+    out.u8(match quirks.index_increment {
+        IndexIncrement::Unchanged => 0,
+        IndexIncrement::X => 1,
+        IndexIncrement::XPlusOne => 2,
+    });
+    out.bool(quirks.jump_vx);
+    out.bool(quirks.vf_reset);
+    out.bool(quirks.display_wait);
+    out.bool(quirks.clip_sprites);
+    out.bool(quirks.wait_key_press);
+    out.bool(quirks.collision_rows);
+}
+
+pub(crate) fn read_platform(input: &mut Reader<'_>) -> Result<(Platform, Quirks), StateError> {
+    let platform = match input.u8()? {
+        0 => Platform::Chip8,
+        1 => Platform::Chip48,
+        2 => Platform::SuperChip,
+        3 => Platform::XoChip,
+        _ => return Err(StateError::Malformed("unknown platform")),
+    };
+    let quirks = Quirks {
+        shift_vx: input.bool()?,
+        index_increment: match input.u8()? {
+            0 => IndexIncrement::Unchanged,
+            1 => IndexIncrement::X,
+            2 => IndexIncrement::XPlusOne,
+            _ => return Err(StateError::Malformed("unknown index increment quirk")),
+        },
+        jump_vx: input.bool()?,
+        vf_reset: input.bool()?,
+        display_wait: input.bool()?,
+        clip_sprites: input.bool()?,
+        wait_key_press: input.bool()?,
+        collision_rows: input.bool()?,
+    };
+    Ok((platform, quirks))
+}
+
+pub(crate) fn write_chip8(out: &mut Writer, chip8: &Chip8) {
+    out.bytes(&chip8.mem);
+
+    // The whole stack, including stale entries above the pointer
+    let (data, len) = chip8.stack.raw_parts();
+    for &addr in data {
+        out.u16(addr);
+    }
+    // Unwrap is ok, the stack is tiny
+    out.u8(u8::try_from(len).unwrap());
+
+    for reg in Register::iter_until(Register::VF) {
+        out.u8(chip8.registers[reg]);
+    }
+    out.u16(chip8.registers.index);
+    out.u16(chip8.pc);
+    out.u8(chip8.delay_timer);
+    out.u8(chip8.sound_timer);
+    out.bytes(&chip8.flags);
+
+    out.bool(chip8.audio_pattern.is_some());
+    out.bytes(&chip8.audio_pattern.unwrap_or_default());
+    out.u8(chip8.audio_pitch);
+}
+
+pub(crate) fn read_chip8(input: &mut Reader<'_>, platform: Platform) -> Result<Chip8, StateError> {
+    let mem = input.bytes()?.to_vec();
+    if mem.len() != platform.memory_size() {
+        return Err(StateError::Malformed(
+            "memory size doesn't match the platform",
+        ));
+    }
+
+    let mut data = [0; Stack::CAPACITY];
+    for addr in &mut data {
+        *addr = input.u16()?;
+    }
+    let stack = Stack::from_raw_parts(data, usize::from(input.u8()?))
+        .ok_or(StateError::Malformed("stack pointer out of range"))?;
+
+    let mut registers = Registers::new();
+    for reg in Register::iter_until(Register::VF) {
+        registers[reg] = input.u8()?;
+    }
+    registers.index = input.u16()?;
+
+    let pc = input.u16()?;
+    let delay_timer = input.u8()?;
+    let sound_timer = input.u8()?;
+    let flags = input
+        .bytes()?
+        .try_into()
+        .map_err(|_| StateError::Malformed("wrong number of flags"))?;
+
+    let has_pattern = input.bool()?;
+    let pattern = input
+        .bytes()?
+        .try_into()
+        .map_err(|_| StateError::Malformed("wrong audio pattern length"))?;
+    let audio_pitch = input.u8()?;
+
+    Ok(Chip8 {
+        mem,
+        stack,
+        registers,
+        pc,
+        delay_timer,
+        sound_timer,
+        flags,
+        audio_pattern: has_pattern.then_some(pattern),
+        audio_pitch,
+        accesses: Vec::new(),
+        // Seeded by the machine, which saves it last
+        rng: fastrand::Rng::with_seed(0),
+    })
+}
+
+pub(crate) fn write_framebuffer(out: &mut Writer, framebuffer: &Framebuffer) {
+    out.bool(framebuffer.is_hires());
+    out.u8(framebuffer.selected_planes());
+    out.bytes(&framebuffer.pixels());
+}
+
+pub(crate) fn read_framebuffer(input: &mut Reader<'_>) -> Result<Framebuffer, StateError> {
+    let hires = input.bool()?;
+    let planes = input.u8()?;
+    let pixels = input.bytes()?;
+    Framebuffer::from_raw_parts(pixels, hires, planes).ok_or(StateError::Malformed(
+        "framebuffer size doesn't match its resolution",
+    ))
+}
+
+/// Only FX0A's progress is saved, the keys held come from the frontend
+pub(crate) fn write_keypad(out: &mut Writer, keypad: &Keypad) {
+    out.bool(keypad.previous.is_some());
+    out.u16(keys_to_bits(keypad.previous.unwrap_or_default()));
+    out.u16(keys_to_bits(keypad.pressed));
+}
+
+/// Returns the keys FX0A last saw held, if it's waiting, and the keys pressed during the wait
+pub(crate) fn read_keypad(
+    input: &mut Reader<'_>,
+) -> Result<(Option<[bool; 16]>, [bool; 16]), StateError> {
+    let waiting = input.bool()?;
+    let previous = keys_from_bits(input.u16()?);
+    let pressed = keys_from_bits(input.u16()?);
+    Ok((waiting.then_some(previous), pressed))
+}
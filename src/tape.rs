@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+
+use chip8::{
+    movie::{Frame, Movie},
+    Machine,
+};
+use log::{error, info};
+
+/// A movie being recorded from, or played back instead of, the frontend's input
+///
+/// Frontends call [`Tape::begin_frame`] and [`Tape::end_frame`] around every frame, which ends
+/// with a timer tick, and [`Tape::step`] for every call to [`Machine::step`].
+pub enum Tape {
+    /// Written to `path` once dropped
+    Recording {
+        movie: Movie,
+        path: PathBuf,
+        current: Option<Frame>,
+    },
+    /// Input goes back to the frontend once the movie runs out
+    Playing {
+        movie: Movie,
+        next: usize,
+        current: Option<Frame>,
+    },
+}
+
+impl Tape {
+    pub fn record(machine: &Machine, path: PathBuf) -> Self {
+        info!("Recording movie to {}", path.display());
+        Self::Recording {
+            movie: Movie::record(machine),
+            path,
+            current: None,
+        }
+    }
+
+    /// Restores the movie's start state into `machine`
+    pub fn play(machine: &mut Machine, path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path)
+            .map_err(|err| format!("Failed to read movie from {}: {err}", path.display()))?;
+        let movie = Movie::parse(&bytes)
+            .map_err(|err| format!("Failed to load movie from {}: {err}", path.display()))?;
+        machine
+            .load_state(movie.start_state())
+            .map_err(|err| format!("Failed to load movie from {}: {err}", path.display()))?;
+
+        info!(
+            "Playing {} frames of movie {}",
+            movie.frames().len(),
+            path.display()
+        );
+        Ok(Self::Playing {
+            movie,
+            next: 0,
+            current: None,
+        })
+    }
+
+    /// Frames left to play back, if any
+    pub fn remaining_frames(&self) -> Option<usize> {
+        match self {
+            Self::Recording { .. } => None,
+            Self::Playing { movie, next, .. } => Some(movie.frames().len().saturating_sub(*next)),
+        }
+    }
+
+    /// Starts a frame, returning the keys to hold during it
+    ///
+    /// Recordings hold the `live` keys from the frontend.
+    pub fn begin_frame(&mut self, live: [bool; 16]) -> [bool; 16] {
+        match self {
+            Self::Recording { current, .. } => {
+                *current = Some(Frame {
+                    keys: live,
+                    instructions: 0,
+                });
+                live
+            }
+            Self::Playing {
+                movie,
+                next,
+                current,
+            } => {
+                *current = movie.frames().get(*next).copied();
+                if current.is_none() && *next == movie.frames().len() {
+                    info!("Movie finished, input is live again");
+                }
+                *next += 1;
+                current.map_or(live, |frame| frame.keys)
+            }
+        }
+    }
+
+    /// Finishes the frame once the timers were ticked
+    pub fn end_frame(&mut self) {
+        match self {
+            Self::Recording { movie, current, .. } => {
+                if let Some(frame) = current.take() {
+                    movie.push(frame);
+                }
+            }
+            Self::Playing { current, .. } => *current = None,
+        }
+    }
+
+    /// Instructions the current frame still has to execute before the next timer tick
+    ///
+    /// `None` if the frontend decides, while recording or once the movie has finished.
+    pub fn instructions_left(&self) -> Option<u32> {
+        match self {
+            Self::Recording { .. } => None,
+            Self::Playing { current, .. } => current.map(|frame| frame.instructions),
+        }
+    }
+
+    /// Counts an executed instruction towards the current frame
+    pub const fn step(&mut self) {
+        match self {
+            Self::Recording {
+                current: Some(frame),
+                ..
+            } => frame.instructions += 1,
+            Self::Playing {
+                current: Some(frame),
+                ..
+            } => frame.instructions = frame.instructions.saturating_sub(1),
+            _ => {}
+        }
+    }
+}
+
+impl Drop for Tape {
+    // A frame cut short by exiting never reached its timer tick, so it's left out
+    fn drop(&mut self) {
+        if let Self::Recording { movie, path, .. } = self {
+            match std::fs::write(&path, movie.to_bytes()) {
+                Ok(()) => info!(
+                    "Saved {} frames of movie to {}",
+                    movie.frames().len(),
+                    path.display()
+                ),
+                Err(err) => error!("Failed to save movie to {}: {err}", path.display()),
+            }
+        }
+    }
+}
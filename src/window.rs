@@ -0,0 +1,350 @@
+use chip8::{
+    audio::{AudioSink, Beeper, Sound},
+    instructions::DisplayModified,
+    rewind::Rewind,
+    Fault, Machine, HIRES_HEIGHT, HIRES_WIDTH,
+};
+use log::error;
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+
+use crate::{
+    debug::{DebugFrontend, State},
+    render,
+    save_slots::Slots,
+    scheduler::{Scheduler, FRAME},
+    settings::Settings,
+    tape::Tape,
+};
+
+/// Plays time backwards while held
+const REWIND_KEY: Key = Key::Backspace;
+/// Raise and lower the number of instructions per frame
+const FASTER_KEY: Key = Key::PageUp;
+const SLOWER_KEY: Key = Key::PageDown;
+/// Pauses and resumes emulation
+const PAUSE_KEY: Key = Key::F5;
+/// Runs a single frame while paused
+const ADVANCE_KEY: Key = Key::F6;
+/// Runs [`FAST_FORWARD`] frames in the time of one while held
+const FAST_FORWARD_KEY: Key = Key::Tab;
+const FAST_FORWARD: u32 = 4;
+
+/// Why the window stopped running instructions
+enum Flow {
+    /// The frame's instructions are done
+    FrameDone,
+    /// The debugger is paused
+    Paused,
+    /// The program faulted without a debugger to inspect it
+    Faulted,
+    Quit,
+}
+
+/// Runs the machine in a minifb window until it is closed
+pub fn run(
+    machine: Machine,
+    settings: &Settings,
+    slots: &Slots,
+    beeper: Beeper,
+    audio_sinks: Vec<Box<dyn AudioSink>>,
+    debugger: Option<Box<dyn DebugFrontend>>,
+    tape: Option<Tape>,
+) {
+    // Jumping around in time would break movies
+    let rewind = if tape.is_some() {
+        Rewind::new(0, 0)
+    } else {
+        settings.rewind.buffer()
+    };
+
+    let mut emulator = Emulator {
+        machine,
+        settings,
+        slots,
+        beeper,
+        audio_sinks,
+        debugger,
+        tape,
+        rewind,
+        scheduler: Scheduler::new(settings.tickrate),
+        window: open_window(),
+        buf: Vec::new(),
+        title: String::new(),
+        display_modified: DisplayModified::Unchanged,
+        rewinding: false,
+        paused: false,
+        advancing: false,
+        executed: 0,
+    };
+    emulator.run();
+}
+
+struct Emulator<'a> {
+    machine: Machine,
+    settings: &'a Settings,
+    slots: &'a Slots,
+    beeper: Beeper,
+    audio_sinks: Vec<Box<dyn AudioSink>>,
+    debugger: Option<Box<dyn DebugFrontend>>,
+    tape: Option<Tape>,
+    rewind: Rewind,
+    scheduler: Scheduler,
+    window: Window,
+    buf: Vec<u32>,
+    title: String,
+    display_modified: DisplayModified,
+    /// Whether the rewind key is held, in which case frames run backwards without instructions
+    rewinding: bool,
+    /// Paused with the pause key, which unlike the debugger still lets frames be advanced
+    paused: bool,
+    /// Running a single frame while paused
+    advancing: bool,
+    /// Instructions run so far in the current frame
+    executed: usize,
+}
+
+impl Emulator<'_> {
+    fn run(&mut self) {
+        let keys = self.settings.keymap.read_keys(&self.window);
+        let keys = self
+            .tape
+            .as_mut()
+            .map_or(keys, |tape| tape.begin_frame(keys));
+        self.machine.set_keys(keys);
+
+        while self.window.is_open() {
+            if self.paused && !self.advancing {
+                // Nothing runs, but hotkeys are still handled
+                self.refresh();
+                self.handle_hotkeys();
+                std::thread::sleep(FRAME);
+                self.scheduler.reset();
+                continue;
+            }
+
+            match self.run_instructions() {
+                Flow::FrameDone => {
+                    self.scheduler.wait_for_frame();
+                    self.end_frame();
+                }
+                Flow::Paused => {
+                    // Timers are frozen too, but the window is kept responsive
+                    self.refresh();
+                    std::thread::sleep(FRAME);
+                    self.scheduler.reset();
+                }
+                Flow::Faulted => break,
+                Flow::Quit => return,
+            }
+        }
+
+        // Keep the window open on a fault, so the final screen can still be inspected
+        while self.window.is_open() {
+            self.window.update();
+            std::thread::sleep(FRAME);
+        }
+    }
+
+    /// Runs the rest of the current frame's instructions
+    fn run_instructions(&mut self) -> Flow {
+        if self.rewinding {
+            return Flow::FrameDone;
+        }
+
+        while !self.frame_done() {
+            if let Some(debugger) = self.debugger.as_mut() {
+                let state = debugger.poll(&mut self.machine);
+                if debugger.take_display_modified() == DisplayModified::Changed {
+                    self.display_modified = DisplayModified::Changed;
+                }
+
+                match state {
+                    State::Running => {}
+                    State::Paused => return Flow::Paused,
+                    State::Quit => return Flow::Quit,
+                }
+            }
+
+            if let Some(tape) = self.tape.as_mut() {
+                tape.step();
+            }
+            self.executed += 1;
+            self.scheduler.count_instruction();
+
+            match self.machine.step() {
+                Ok(DisplayModified::Changed) => self.display_modified = DisplayModified::Changed,
+                Ok(DisplayModified::Unchanged) => {}
+                Err(err) => {
+                    // The debugger can inspect the fault instead
+                    if let Some(debugger) = self.debugger.as_mut() {
+                        debugger.fault(&self.machine, &err);
+                    } else {
+                        self.report_fault(&err);
+                        return Flow::Faulted;
+                    }
+                }
+            }
+        }
+
+        Flow::FrameDone
+    }
+
+    /// A movie's frame only ends once all of its instructions ran
+    fn frame_done(&self) -> bool {
+        self.tape
+            .as_ref()
+            .and_then(Tape::instructions_left)
+            .map_or_else(
+                || self.executed >= self.scheduler.tickrate(),
+                |left| left == 0,
+            )
+    }
+
+    /// Plays the frame's audio, ticks the timers and shows the frame, then reads input for the
+    /// next one
+    fn end_frame(&mut self) {
+        if self.rewinding {
+            // Recordings keep running in real time
+            self.beeper
+                .render_frame(Sound::Silent, &mut self.audio_sinks);
+            step_back(&mut self.machine, &mut self.rewind);
+            self.display_modified = DisplayModified::Changed;
+        } else {
+            self.beeper
+                .render_frame(self.machine.sound(), &mut self.audio_sinks);
+            self.update_title();
+            self.machine.tick_timers();
+            self.rewind.push(self.machine.save_state());
+        }
+        self.refresh();
+
+        // Key presses were just read by the update, and only change between frames
+        self.handle_hotkeys();
+        let keys = self.settings.keymap.read_keys(&self.window);
+        let keys = self.tape.as_mut().map_or(keys, |tape| {
+            tape.end_frame();
+            tape.begin_frame(keys)
+        });
+        self.machine.set_keys(keys);
+        self.executed = 0;
+        self.advancing = false;
+    }
+
+    fn handle_hotkeys(&mut self) {
+        if self.window.is_key_pressed(PAUSE_KEY, KeyRepeat::No) {
+            self.paused = !self.paused;
+            self.update_title();
+        }
+        if self.paused && self.window.is_key_pressed(ADVANCE_KEY, KeyRepeat::Yes) {
+            self.advancing = true;
+        }
+        let fast_forward = self.window.is_key_down(FAST_FORWARD_KEY);
+        if fast_forward != self.scheduler.is_fast_forwarding() {
+            self.scheduler
+                .set_multiplier(if fast_forward { FAST_FORWARD } else { 1 });
+            self.update_title();
+        }
+        if self.window.is_key_pressed(FASTER_KEY, KeyRepeat::Yes) {
+            self.scheduler.faster();
+        }
+        if self.window.is_key_pressed(SLOWER_KEY, KeyRepeat::Yes) {
+            self.scheduler.slower();
+        }
+
+        // Jumping around in time would break movies
+        if self.tape.is_some() {
+            return;
+        }
+        // Frames are only stepped forwards while paused
+        let rewinding = !self.paused && self.window.is_key_down(REWIND_KEY);
+        if rewinding != self.rewinding {
+            self.rewinding = rewinding;
+            self.update_title();
+        }
+        if self.slots.handle_hotkeys(&self.window, &mut self.machine) {
+            self.display_modified = DisplayModified::Changed;
+        }
+    }
+
+    /// Shows what the emulator is doing, and how fast
+    fn update_title(&mut self) {
+        let mut title = if self.paused {
+            "⏸ CHIP-8 Emulator - paused".to_owned()
+        } else if self.rewinding {
+            "⏪ CHIP-8 Emulator".to_owned()
+        } else if self.machine.sound_active() {
+            "🔔🔔 CHIP-8 Emulator 🔔🔔".to_owned()
+        } else {
+            "CHIP-8 Emulator".to_owned()
+        };
+        if self.scheduler.is_fast_forwarding() && !self.paused {
+            title = format!("⏩ {title}");
+        }
+        if let Some((ips, fps)) = self.scheduler.speed().filter(|_| !self.paused) {
+            title = format!("{title} - {ips} IPS, {fps} FPS");
+        }
+
+        if title != self.title {
+            self.window.set_title(&title);
+            self.title = title;
+        }
+    }
+
+    fn report_fault(&mut self, err: &Fault) {
+        let chip8 = self.machine.chip8();
+        error!("Program faulted: {err}");
+        error!(
+            "PC: 0x{:03X}, registers: {:?}, stack: {:?}",
+            chip8.pc, chip8.registers, chip8.stack
+        );
+        self.window
+            .set_title(&format!("CHIP-8 Emulator - faulted: {err}"));
+        self.present();
+    }
+
+    /// Presents the framebuffer if it changed, or otherwise just handles window events
+    fn refresh(&mut self) {
+        if self.display_modified == DisplayModified::Changed {
+            self.present();
+            self.display_modified = DisplayModified::Unchanged;
+        } else {
+            self.window.update();
+        }
+    }
+
+    fn present(&mut self) {
+        let framebuffer = self.machine.framebuffer();
+        render::render(framebuffer, &mut self.buf, &self.settings.colors);
+        self.window
+            .update_with_buffer(&self.buf, framebuffer.width(), framebuffer.height())
+            .expect("failed to update window");
+    }
+}
+
+fn open_window() -> Window {
+    let mut window = Window::new(
+        "CHIP-8 Emulator",
+        HIRES_WIDTH,
+        HIRES_HEIGHT,
+        WindowOptions {
+            resize: false,
+            // Lower resolution framebuffers are stretched to fit
+            scale: minifb::Scale::X8,
+            ..Default::default()
+        },
+    )
+    .expect("failed to create window");
+
+    // We do our own limiting
+    window.limit_update_rate(None);
+    window
+}
+
+/// Restores the state from one frame earlier, staying on the oldest one once history runs out
+fn step_back(machine: &mut Machine, rewind: &mut Rewind) {
+    if let Some(state) = rewind.step_back() {
+        if let Err(err) = machine.load_state(state) {
+            error!("Failed to rewind: {err}");
+        }
+    }
+}
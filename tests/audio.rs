@@ -0,0 +1,126 @@
+use chip8::{
+    audio::{AudioSink, Beeper, Sound, WavSink, SAMPLES_PER_FRAME, SAMPLE_RATE},
+    platform::Platform,
+    Machine,
+};
+
+/// Keeps everything written to it
+#[derive(Default)]
+struct Recording(Vec<f32>);
+
+impl AudioSink for Recording {
+    fn write(&mut self, samples: &[f32]) {
+        self.0.extend(samples);
+    }
+}
+
+/// Runs frames like the headless frontend, rendering each frame's audio before ticking timers
+fn record(machine: &mut Machine, beeper: &mut Beeper, frames: usize, sink: &mut dyn AudioSink) {
+    for _ in 0..frames {
+        for _ in 0..10 {
+            machine.step().unwrap();
+        }
+        beeper.render_frame(machine.sound(), sink);
+        machine.tick_timers();
+    }
+}
+
+/// Lengths of the runs of equal samples
+fn runs(samples: &[f32]) -> Vec<usize> {
+    samples.chunk_by(|a, b| a == b).map(<[f32]>::len).collect()
+}
+
+#[test]
+fn the_sound_timer_beeps_for_its_frames() {
+    // ST = 5, then loops forever
+    let program = [0x60, 0x05, 0xF0, 0x18, 0x12, 0x04];
+    let mut machine = Machine::new(&program, Platform::Chip8).unwrap();
+    // 100 samples per period
+    let mut beeper = Beeper::new(441.0, 0.5);
+    let mut recording = Recording::default();
+    record(&mut machine, &mut beeper, 8, &mut recording);
+
+    let samples = recording.0;
+    assert_eq!(samples.len(), 8 * SAMPLES_PER_FRAME);
+    let (tone, silence) = samples.split_at(5 * SAMPLES_PER_FRAME);
+    assert!(silence.iter().all(|&sample| sample == 0.0));
+    assert!(tone.iter().all(|&sample| sample == 0.5 || sample == -0.5));
+    assert_eq!(tone[0], 0.5);
+
+    // Half periods, apart from the cut off last one
+    let runs = runs(tone);
+    let (last, halves) = runs.split_last().unwrap();
+    assert!(halves.iter().all(|&run| run.abs_diff(50) <= 1), "{runs:?}");
+    assert!(*last <= 51);
+    assert_eq!(halves.len(), 5 * SAMPLES_PER_FRAME / 50);
+}
+
+#[test]
+fn beeps_can_be_recorded_to_wav() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("beep.wav");
+    let program = [0x60, 0x02, 0xF0, 0x18, 0x12, 0x04];
+    let mut machine = Machine::new(&program, Platform::Chip8).unwrap();
+    let mut beeper = Beeper::new(441.0, 0.25);
+    let mut wav = WavSink::create(&path).unwrap();
+    record(&mut machine, &mut beeper, 3, &mut wav);
+    wav.finish().unwrap();
+
+    let mut reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.spec().sample_rate, SAMPLE_RATE);
+    assert_eq!(reader.spec().channels, 1);
+    let samples: Vec<i16> = reader.samples().map(Result::unwrap).collect();
+    assert_eq!(samples.len(), 3 * SAMPLES_PER_FRAME);
+
+    let loud = i16::MAX / 4;
+    let (tone, silence) = samples.split_at(2 * SAMPLES_PER_FRAME);
+    assert!(tone.iter().all(|&sample| sample == loud || sample == -loud));
+    assert!(silence.iter().all(|&sample| sample == 0));
+}
+
+#[test]
+fn patterns_play_at_their_pitch_until_the_timer_ends() {
+    let mut pattern = [0; 16];
+    pattern[0] = 0xFF;
+    let mut program = vec![
+        0xA2, 0x0E, // I = pattern
+        0xF0, 0x02, // Load the audio pattern
+        0x60, 112, // V0 = 112
+        0xF0, 0x3A, // Pitch = V0
+        0x60, 0x02, // ST = 2
+        0xF0, 0x18, //
+        0x12, 0x0C, // Loop forever
+    ];
+    program.extend(pattern);
+    let mut machine = Machine::new(&program, Platform::XoChip).unwrap();
+    assert_eq!(
+        machine.sound(),
+        Sound::Silent,
+        "nothing should play before ST is set"
+    );
+
+    let mut beeper = Beeper::new(441.0, 0.5);
+    let mut recording = Recording::default();
+    record(&mut machine, &mut beeper, 3, &mut recording);
+    let samples = recording.0;
+
+    // 4000 * 2^((112 - 64) / 48) = 8000 bits per second, so each bit lasts 5.5125 samples
+    for (pitch, rate) in [(16, 2000.0), (64, 4000.0), (112, 8000.0), (160, 16000.0)] {
+        assert!((Sound::pattern_rate(pitch) - rate).abs() < 0.01, "{pitch}");
+    }
+    let samples_per_bit = 44100.0 / 8000.0;
+    let period = 128.0 * samples_per_bit;
+
+    // The first byte is played first, from its top bit, and repeats every 128 bits
+    let (tone, silence) = samples.split_at(2 * SAMPLES_PER_FRAME);
+    for (idx, &sample) in tone.iter().enumerate() {
+        #[allow(clippy::cast_precision_loss)]
+        let bit = (idx as f64 % period) / samples_per_bit;
+        // Edges may land a sample either way
+        if (bit - bit.round()).abs() * samples_per_bit < 1.0 {
+            continue;
+        }
+        let expected = if bit < 8.0 { 0.5 } else { -0.5 };
+        assert_eq!(sample, expected, "sample {idx}, bit {bit}");
+    }
+    assert!(silence.iter().all(|&sample| sample == 0.0));
+}
@@ -0,0 +1,123 @@
+use chip8::{
+    database::{self, Database},
+    platform::Platform,
+    quirks::{IndexIncrement, Quirks},
+};
+
+/// CLS, then loops forever
+const ROM: &[u8] = &[0x00, 0xE0, 0x12, 0x02];
+const ROM_HASH: &str = "ebb9deb484be6f9599690d2cc276670112a66636";
+
+/// An entry in the layout of chip-8-database's `programs.json`, keyed by [`ROM_HASH`] in
+/// uppercase, whose first platform can't be run
+const PROGRAMS: &str = r##"[
+    {
+        "title": "Clear Screen",
+        "roms": {
+            "EBB9DEB484BE6F9599690D2CC276670112A66636": {
+                "file": "cls.ch8",
+                "platforms": ["megachip8", "superchip"],
+                "quirkyPlatforms": {
+                    "superchip": {"wrap": true, "memoryLeaveIUnchanged": false, "logic": true}
+                },
+                "tickrate": 30,
+                "colors": {"pixels": ["#101010", "#AABBCC"]},
+                "keys": {"up": 5, "a": 6, "b": 16}
+            }
+        }
+    },
+    {
+        "title": "MEGA-CHIP only",
+        "roms": {
+            "0000000000000000000000000000000000000000": {"platforms": ["megachip8"]}
+        }
+    }
+]"##;
+
+#[test]
+fn hashes_roms_as_lowercase_sha1() {
+    assert_eq!(database::rom_hash(ROM), ROM_HASH);
+}
+
+#[test]
+fn detects_platform_quirks_and_tickrate() {
+    let database = Database::parse(PROGRAMS).unwrap();
+    let rom = database.lookup(&database::rom_hash(ROM)).unwrap();
+
+    assert_eq!(rom.title, "Clear Screen");
+    assert_eq!(rom.platform, Platform::SuperChip);
+    assert_eq!(
+        rom.quirks,
+        Quirks {
+            clip_sprites: false,
+            vf_reset: true,
+            index_increment: IndexIncrement::XPlusOne,
+            ..Quirks::SUPER_CHIP
+        }
+    );
+    assert_eq!(rom.tickrate, Some(30));
+    assert_eq!(rom.background, Some(0x10_10_10));
+    assert_eq!(rom.foreground, Some(0xAA_BB_CC));
+    assert_eq!(rom.plane2, None);
+    // Keys past F are dropped
+    assert_eq!(rom.keys.len(), 2);
+    assert_eq!(rom.keys["up"], 5);
+}
+
+#[test]
+fn index_increment_comes_from_both_memory_quirks() {
+    let index_increment = |platform: &str, overrides: &str| {
+        let json = format!(
+            r#"[{{"title": "", "roms": {{"{ROM_HASH}": {{
+                "platforms": ["{platform}"],
+                "quirkyPlatforms": {{"{platform}": {{{overrides}}}}}
+            }}}}}}]"#
+        );
+        let database = Database::parse(&json).unwrap();
+        database.lookup(ROM_HASH).unwrap().quirks.index_increment
+    };
+
+    assert_eq!(
+        index_increment("superchip", r#""memoryLeaveIUnchanged": false"#),
+        IndexIncrement::XPlusOne
+    );
+    assert_eq!(
+        index_increment(
+            "superchip",
+            r#""memoryLeaveIUnchanged": false, "memoryIncrementByX": true"#
+        ),
+        IndexIncrement::X
+    );
+    // Leaving I unchanged wins over incrementing it
+    assert_eq!(
+        index_increment(
+            "originalChip8",
+            r#""memoryIncrementByX": true, "memoryLeaveIUnchanged": true"#
+        ),
+        IndexIncrement::Unchanged
+    );
+    assert_eq!(
+        index_increment("chip48", r#""memoryIncrementByX": false"#),
+        IndexIncrement::XPlusOne
+    );
+    assert_eq!(index_increment("chip48", ""), IndexIncrement::X);
+}
+
+#[test]
+fn skips_roms_for_unsupported_platforms() {
+    let database = Database::parse(PROGRAMS).unwrap();
+    assert!(database
+        .lookup("0000000000000000000000000000000000000000")
+        .is_none());
+}
+
+#[test]
+fn rejects_malformed_databases() {
+    assert!(Database::parse("{}").is_err());
+    assert!(Database::parse(r#"[{"roms": {}}]"#).is_err());
+}
+
+#[test]
+fn bundled_database_parses() {
+    let _ = Database::bundled();
+}
@@ -0,0 +1,269 @@
+use chip8::{
+    chip8::{AccessKind, MemAccess},
+    debugger::{Condition, Debugger, Stop, Watch, Watchpoint},
+    platform::Platform,
+    registers::Register,
+    Machine,
+};
+
+/// 0x200: call 0x206, 0x202: V0 = 1, 0x204: loop forever, 0x206: V1 = 2, 0x208: return
+const PROGRAM: &[u16] = &[0x2206, 0x6001, 0x1204, 0x6102, 0x00EE];
+
+fn machine(program: &[u16]) -> Machine {
+    let prg: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    Machine::new(&prg, Platform::Chip8).unwrap()
+}
+
+/// Runs like a frontend would, checking the debugger before every instruction
+fn run_until_stop(debugger: &mut Debugger, machine: &mut Machine) -> Stop {
+    for _ in 0..100 {
+        if let Some(stop) = debugger.check(machine) {
+            return stop;
+        }
+        machine.step().unwrap();
+    }
+    panic!("debugger never stopped");
+}
+
+#[test]
+fn stops_at_breakpoints() {
+    let mut machine = machine(PROGRAM);
+    let mut debugger = Debugger::new();
+    assert!(debugger.add_breakpoint(0x202, None));
+    assert!(!debugger.add_breakpoint(0x202, None));
+
+    debugger.resume(&mut machine).unwrap();
+    assert_eq!(
+        run_until_stop(&mut debugger, &mut machine),
+        Stop::Breakpoint { addr: 0x202 }
+    );
+    assert!(debugger.is_paused());
+    assert_eq!(machine.chip8().pc, 0x202);
+}
+
+#[test]
+fn resuming_steps_past_the_breakpoint_at_pc() {
+    let mut machine = machine(PROGRAM);
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x200, None);
+    debugger.add_breakpoint(0x206, None);
+
+    assert_eq!(
+        debugger.check(&machine),
+        Some(Stop::Breakpoint { addr: 0x200 })
+    );
+    debugger.resume(&mut machine).unwrap();
+    assert_eq!(
+        run_until_stop(&mut debugger, &mut machine),
+        Stop::Breakpoint { addr: 0x206 }
+    );
+}
+
+#[test]
+fn removed_breakpoints_no_longer_stop() {
+    let mut machine = machine(PROGRAM);
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x206, None);
+    debugger.add_breakpoint(0x204, None);
+    assert!(debugger.remove_breakpoint(0x206));
+    assert!(!debugger.remove_breakpoint(0x206));
+
+    debugger.resume(&mut machine).unwrap();
+    assert_eq!(
+        run_until_stop(&mut debugger, &mut machine),
+        Stop::Breakpoint { addr: 0x204 }
+    );
+}
+
+#[test]
+fn step_over_runs_the_whole_call() {
+    let mut machine = machine(PROGRAM);
+    let mut debugger = Debugger::new();
+
+    debugger.step_over(&mut machine).unwrap();
+    assert_eq!(run_until_stop(&mut debugger, &mut machine), Stop::StepOver);
+    assert_eq!(machine.chip8().pc, 0x202);
+    assert_eq!(machine.chip8().registers[Register::V1], 2);
+}
+
+#[test]
+fn finish_runs_until_the_subroutine_returns() {
+    let mut machine = machine(PROGRAM);
+    let mut debugger = Debugger::new();
+
+    debugger.step(&mut machine).unwrap();
+    assert_eq!(machine.chip8().pc, 0x206);
+    debugger.finish(&mut machine).unwrap();
+    assert_eq!(run_until_stop(&mut debugger, &mut machine), Stop::Finish);
+    assert_eq!(machine.chip8().pc, 0x202);
+    assert!(machine.chip8().stack.as_slice().is_empty());
+}
+
+fn condition(source: &str) -> Condition {
+    source
+        .parse()
+        .unwrap_or_else(|err| panic!("{source}: {err}"))
+}
+
+fn set(machine: &mut Machine, values: [u8; 3]) {
+    let registers = &mut machine.chip8_mut().registers;
+    registers[Register::V0] = values[0];
+    registers[Register::V1] = values[1];
+    registers[Register::V2] = values[2];
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    let mut machine = machine(PROGRAM);
+    let condition = condition("V0 == 1 || V1 == 2 && V2 == 3");
+
+    set(&mut machine, [1, 0, 0]);
+    assert!(condition.eval(&machine));
+    set(&mut machine, [0, 2, 0]);
+    assert!(!condition.eval(&machine));
+    set(&mut machine, [0, 2, 3]);
+    assert!(condition.eval(&machine));
+}
+
+#[test]
+fn conditions_compare_registers_and_numbers() {
+    let mut machine = machine(PROGRAM);
+    machine.chip8_mut().registers[Register::VA] = 0x10;
+    machine.chip8_mut().registers.index = 0x300;
+    machine.chip8_mut().delay_timer = 5;
+
+    // Names are case-insensitive, and operators don't need spaces
+    assert!(condition("va>=0x10").eval(&machine));
+    assert!(!condition("VA > 16").eval(&machine));
+    assert!(condition("VA != V0").eval(&machine));
+    assert!(condition("I == 0x300 && PC == 0x200").eval(&machine));
+    assert!(condition("dt <= 5 && ST < 1").eval(&machine));
+}
+
+#[test]
+fn sp_is_the_stack_depth() {
+    let mut machine = machine(PROGRAM);
+    let in_call = condition("sp == 1");
+    assert!(!in_call.eval(&machine));
+    machine.step().unwrap();
+    assert!(in_call.eval(&machine));
+}
+
+#[test]
+fn conditions_display_normalized() {
+    assert_eq!(
+        condition("  V0  ==  1   &&I>2 ").to_string(),
+        "V0 == 1 &&I>2"
+    );
+}
+
+#[test]
+fn bad_conditions_are_errors() {
+    for source in [
+        "",
+        "V0",
+        "V0 ==",
+        "V0 == 1 &&",
+        "VG == 1",
+        "V10 == 1",
+        "V0 =~ 1",
+        "V0 == 0xZZ",
+        "V0 == 70000",
+        "V0 == 1 2",
+    ] {
+        assert!(source.parse::<Condition>().is_err(), "{source}");
+    }
+}
+
+#[test]
+fn conditional_breakpoints_only_stop_when_true() {
+    // Counts V0 up in a loop at 0x200
+    let mut machine = machine(&[0x7001, 0x1200]);
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x200, Some(condition("V0 == 3")));
+
+    debugger.resume(&mut machine).unwrap();
+    assert_eq!(
+        run_until_stop(&mut debugger, &mut machine),
+        Stop::Breakpoint { addr: 0x200 }
+    );
+    assert_eq!(machine.chip8().registers[Register::V0], 3);
+}
+
+#[test]
+fn conditions_stop_anywhere() {
+    let mut machine = machine(PROGRAM);
+    let mut debugger = Debugger::new();
+    debugger.add_condition(condition("V1 == 2"));
+
+    debugger.resume(&mut machine).unwrap();
+    assert_eq!(run_until_stop(&mut debugger, &mut machine), Stop::Condition);
+    assert_eq!(machine.chip8().pc, 0x208);
+}
+
+#[test]
+fn watchpoints_stop_after_matching_accesses() {
+    // I = 0x300, V0 = 5, store V0 at I, then load it back
+    let program = [0xA300, 0x6005, 0xF055, 0xA300, 0xF065, 0x120A];
+    let write = MemAccess {
+        kind: AccessKind::Write,
+        addr: 0x300,
+        len: 1,
+    };
+
+    let mut machine = machine(&program);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint {
+        start: 0x2FF,
+        end: 0x300,
+        watch: Watch::Write,
+    });
+    debugger.resume(&mut machine).unwrap();
+    assert_eq!(
+        run_until_stop(&mut debugger, &mut machine),
+        Stop::Watchpoint {
+            pc: 0x204,
+            access: write,
+        }
+    );
+
+    // Reads of the same memory don't trigger a write watchpoint, but do trigger a read one
+    let mut machine = self::machine(&program);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint {
+        start: 0x300,
+        end: 0x300,
+        watch: Watch::Read,
+    });
+    debugger.resume(&mut machine).unwrap();
+    assert_eq!(
+        run_until_stop(&mut debugger, &mut machine),
+        Stop::Watchpoint {
+            pc: 0x208,
+            access: MemAccess {
+                kind: AccessKind::Read,
+                ..write
+            },
+        }
+    );
+}
+
+#[test]
+fn watchpoints_match_overlapping_ranges() {
+    let watchpoint = Watchpoint {
+        start: 0x300,
+        end: 0x30F,
+        watch: Watch::Access,
+    };
+    let access = |addr, len| MemAccess {
+        kind: AccessKind::Read,
+        addr,
+        len,
+    };
+
+    assert!(watchpoint.is_triggered_by(&access(0x2F0, 0x11)));
+    assert!(watchpoint.is_triggered_by(&access(0x30F, 4)));
+    assert!(!watchpoint.is_triggered_by(&access(0x2F0, 0x10)));
+    assert!(!watchpoint.is_triggered_by(&access(0x310, 4)));
+    assert!(!watchpoint.is_triggered_by(&access(0x300, 0)));
+}
@@ -0,0 +1,218 @@
+use chip8::{
+    display::PLANE_1, platform::Platform, quirks::Quirks, registers::Register, Fault, Machine,
+};
+
+/// Address of the built-in font's "0", a 4x5 box: F0 90 90 90 F0
+const FONT_ZERO: u16 = 0x050;
+
+fn machine(program: &[u16], platform: Platform) -> Machine {
+    let prg: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut machine = Machine::new(&prg, platform).unwrap();
+    machine.set_quirks(platform.quirks());
+    machine
+}
+
+/// Executes `count` instructions, ending the frame whenever a draw waits for the vertical blank
+fn run(machine: &mut Machine, count: usize) -> Result<(), Fault> {
+    for _ in 0..count {
+        if machine.is_waiting_for_vblank() {
+            machine.tick_timers();
+        }
+        machine.step()?;
+    }
+    Ok(())
+}
+
+fn vf(machine: &Machine) -> u8 {
+    machine.chip8().registers[Register::VF]
+}
+
+#[test]
+fn no_collision_clears_vf() {
+    // V0 = 0, VF = 1, I = font "0", draw at (0, 0)
+    let mut machine = machine(
+        &[0x6000, 0x6F01, 0xA000 | FONT_ZERO, 0xD005],
+        Platform::Chip8,
+    );
+    run(&mut machine, 4).unwrap();
+    assert_eq!(vf(&machine), 0);
+    assert_eq!(machine.framebuffer().get(0, 0), PLANE_1);
+}
+
+#[test]
+fn collision_anywhere_in_sprite_sets_vf() {
+    // Draw a single pixel at (1, 0), then the "0" box over it. The box's top row covers the
+    // pixel, but its last drawn pixel doesn't collide.
+    let mut machine = machine(
+        &[
+            0x6001,
+            0x6100,
+            0xA20E,
+            0xD011,
+            0x6000,
+            0xA000 | FONT_ZERO,
+            0xD015,
+            // Pixel sprite, the leftmost bit only
+            0x8000,
+        ],
+        Platform::Chip8,
+    );
+    run(&mut machine, 4).unwrap();
+    assert_eq!(vf(&machine), 0);
+    run(&mut machine, 3).unwrap();
+    assert_eq!(vf(&machine), 1);
+    assert_eq!(machine.framebuffer().get(1, 0), 0);
+}
+
+#[test]
+fn redrawing_erases_and_collides() {
+    let mut machine = machine(
+        &[0xA000 | FONT_ZERO, 0x6005, 0xD005, 0xD005],
+        Platform::Chip8,
+    );
+    run(&mut machine, 4).unwrap();
+    assert_eq!(vf(&machine), 1);
+    assert!(machine
+        .framebuffer()
+        .pixels()
+        .iter()
+        .all(|&pixel| pixel == 0));
+}
+
+#[test]
+fn clipped_pixels_never_collide() {
+    // Draw the box at x = 62, then a pixel at x = 0, where the box would have wrapped to
+    let mut machine = machine(
+        &[
+            0xA000 | FONT_ZERO,
+            0x603E,
+            0x6100,
+            0xD015,
+            0x6000,
+            0xA210,
+            0xD011,
+            0x120E,
+            0x8000,
+        ],
+        Platform::Chip8,
+    );
+    run(&mut machine, 4).unwrap();
+    assert_eq!(machine.framebuffer().get(63, 0), PLANE_1);
+    assert_eq!(machine.framebuffer().get(0, 0), 0);
+    run(&mut machine, 3).unwrap();
+    assert_eq!(vf(&machine), 0);
+}
+
+#[test]
+fn wrapped_pixels_collide() {
+    let mut machine = machine(
+        &[
+            0xA000 | FONT_ZERO,
+            0x603E,
+            0x6100,
+            0xD015,
+            0x6000,
+            0xA210,
+            0xD011,
+            0x120E,
+            0x8000,
+        ],
+        Platform::XoChip,
+    );
+    run(&mut machine, 4).unwrap();
+    assert_eq!(machine.framebuffer().get(0, 0), PLANE_1);
+    run(&mut machine, 3).unwrap();
+    assert_eq!(vf(&machine), 1);
+}
+
+#[test]
+fn collision_on_any_plane_sets_vf() {
+    // Draw the box on the first plane, then on both planes with separate sprite data. Only the
+    // first plane's data overlaps it.
+    let mut machine = machine(
+        &[
+            0xA000 | FONT_ZERO,
+            0xD005,
+            0xF301,
+            0xA20C,
+            0xD001,
+            0x120A,
+            // First plane: the box's top left pixel, second plane: blank
+            0x8000,
+        ],
+        Platform::XoChip,
+    );
+    run(&mut machine, 5).unwrap();
+    assert_eq!(vf(&machine), 1);
+}
+
+#[test]
+fn sprite_past_end_of_memory_faults() {
+    // VF = 1, I = 0xFFE, then draw 5 rows, only 2 of which are in memory
+    let mut machine = machine(&[0x6F01, 0xAFFE, 0xD005], Platform::Chip8);
+    run(&mut machine, 2).unwrap();
+    assert_eq!(
+        run(&mut machine, 1),
+        Err(Fault::MemoryOutOfBounds { addr: 0x1000 })
+    );
+    // The draw had no effect, and can be inspected at the program counter
+    assert_eq!(vf(&machine), 1);
+    assert_eq!(machine.chip8().pc, 0x204);
+    assert!(machine
+        .framebuffer()
+        .pixels()
+        .iter()
+        .all(|&pixel| pixel == 0));
+}
+
+#[test]
+fn sprite_at_end_of_memory_draws() {
+    let mut machine = machine(&[0xAFFB, 0xD005], Platform::Chip8);
+    run(&mut machine, 2).unwrap();
+    assert_eq!(vf(&machine), 0);
+}
+
+#[test]
+fn super_chip_hires_counts_collided_rows() {
+    // Switch to hires, then draw the box twice at (0, 0)
+    let mut machine = machine(
+        &[0x00FF, 0xA000 | FONT_ZERO, 0xD005, 0xD005],
+        Platform::SuperChip,
+    );
+    run(&mut machine, 3).unwrap();
+    assert_eq!(vf(&machine), 0);
+    run(&mut machine, 1).unwrap();
+    assert_eq!(vf(&machine), 5);
+}
+
+#[test]
+fn super_chip_hires_counts_rows_clipped_at_bottom() {
+    // Draw the box at y = 61, leaving 2 of its rows below the screen
+    let mut machine = machine(
+        &[0x00FF, 0xA000 | FONT_ZERO, 0x613D, 0xD015],
+        Platform::SuperChip,
+    );
+    run(&mut machine, 4).unwrap();
+    assert_eq!(vf(&machine), 2);
+}
+
+#[test]
+fn super_chip_lores_sets_vf_to_one() {
+    let mut machine = machine(&[0xA000 | FONT_ZERO, 0xD005, 0xD005], Platform::SuperChip);
+    run(&mut machine, 3).unwrap();
+    assert_eq!(vf(&machine), 1);
+}
+
+#[test]
+fn collision_rows_quirk_can_be_disabled() {
+    let mut machine = machine(
+        &[0x00FF, 0xA000 | FONT_ZERO, 0xD005, 0xD005],
+        Platform::SuperChip,
+    );
+    machine.set_quirks(Quirks {
+        collision_rows: false,
+        ..Quirks::SUPER_CHIP
+    });
+    run(&mut machine, 4).unwrap();
+    assert_eq!(vf(&machine), 1);
+}
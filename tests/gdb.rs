@@ -0,0 +1,197 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+/// 0x200: V0 = 0x12, 0x202: I = 0x300, 0x204: loop forever
+const PROGRAM: &[u8] = &[0x60, 0x12, 0xA3, 0x00, 0x12, 0x04];
+
+/// The emulator running headless with a GDB server, and a connection to it
+struct Gdb {
+    emulator: Child,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Gdb {
+    fn start(name: &str) -> Self {
+        Self::start_with(name, PROGRAM, &["--frames", "1"])
+    }
+
+    fn start_with(name: &str, program: &[u8], args: &[&str]) -> Self {
+        let rom = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.ch8"));
+        std::fs::write(&rom, program).unwrap();
+
+        // Let the OS pick a port nothing else is using
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut emulator = Command::new(env!("CARGO_BIN_EXE_chip8"))
+            .args(["--headless", "--mute"])
+            .args(args)
+            .arg("--gdb")
+            .arg(port.to_string())
+            .arg(&rom)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        for _ in 0..500 {
+            if let Ok(stream) = TcpStream::connect((Ipv4Addr::LOCALHOST, port)) {
+                stream
+                    .set_read_timeout(Some(Duration::from_secs(10)))
+                    .unwrap();
+                let mut gdb = Self {
+                    emulator,
+                    reader: BufReader::new(stream.try_clone().unwrap()),
+                    writer: stream,
+                };
+                assert_eq!(gdb.request("QStartNoAckMode"), "OK");
+                return gdb;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = emulator.kill();
+        let _ = emulator.wait();
+        panic!("GDB server never started");
+    }
+
+    /// Sends a packet and returns the reply
+    fn request(&mut self, data: &str) -> String {
+        let sum = data.bytes().fold(0_u8, |sum, byte| sum.wrapping_add(byte));
+        write!(self.writer, "${data}#{sum:02x}").unwrap();
+
+        let mut packet = Vec::new();
+        loop {
+            let mut byte = [0];
+            self.reader.read_exact(&mut byte).unwrap();
+            // Skips acknowledgements
+            if byte[0] == b'$' {
+                break;
+            }
+        }
+        self.reader.read_until(b'#', &mut packet).unwrap();
+        packet.pop();
+        let mut sum = [0; 2];
+        self.reader.read_exact(&mut sum).unwrap();
+        String::from_utf8(packet).unwrap()
+    }
+}
+
+impl Drop for Gdb {
+    fn drop(&mut self) {
+        let _ = self.emulator.kill();
+        let _ = self.emulator.wait();
+    }
+}
+
+#[test]
+fn reads_and_writes_registers() {
+    let mut gdb = Gdb::start("registers");
+    // V0 to VF, then I, PC and SP
+    let initial = format!("{}{}{}{}", "00".repeat(16), "0000", "0200", "00");
+    assert_eq!(gdb.request("g"), initial);
+
+    assert_eq!(gdb.request("s"), "S05");
+    assert_eq!(gdb.request("g"), format!("12{}0000020200", "00".repeat(15)));
+
+    let written = format!("{}{}{}", "ab".repeat(16), "0345", "0204");
+    assert_eq!(gdb.request(&format!("G{written}")), "OK");
+    assert_eq!(gdb.request("g"), format!("{written}00"));
+    assert_eq!(gdb.request("p10"), "0345");
+    // Too short
+    assert_eq!(gdb.request("G00"), "E01");
+}
+
+#[test]
+fn reads_and_writes_memory() {
+    let mut gdb = Gdb::start("memory");
+    assert_eq!(gdb.request("m200,6"), "6012a3001204");
+    // Font data for 0
+    assert_eq!(gdb.request("m50,5"), "f0909090f0");
+
+    assert_eq!(gdb.request("M300,3:0a0b0c"), "OK");
+    assert_eq!(gdb.request("m2ff,5"), "000a0b0c00");
+    // Fewer bytes than the length
+    assert_eq!(gdb.request("M300,3:0a"), "E01");
+
+    // Out of bounds, including ranges whose end overflows
+    assert_eq!(gdb.request("mfff,2"), "E01");
+    assert_eq!(gdb.request("m1000,1"), "E01");
+    assert_eq!(gdb.request("mffffffffffffffff,10"), "E01");
+    assert_eq!(gdb.request("Mffffffffffffffff,1:00"), "E01");
+    assert_eq!(gdb.request("m200"), "E01");
+}
+
+#[test]
+fn stops_at_software_breakpoints() {
+    let mut gdb = Gdb::start("breakpoints");
+    assert_eq!(gdb.request("Z0,204,2"), "OK");
+    assert_eq!(gdb.request("c"), "S05");
+    assert_eq!(gdb.request("p11"), "0204");
+
+    assert_eq!(gdb.request("z0,204,2"), "OK");
+    assert_eq!(gdb.request("Z0,zz,2"), "E01");
+    // Hardware breakpoints fall back to software ones
+    assert_eq!(gdb.request("Z1,204,2"), "E01");
+}
+
+#[test]
+fn serves_the_target_description() {
+    let mut gdb = Gdb::start("target");
+    assert!(gdb.request("qSupported").contains("qXfer:features:read+"));
+
+    let first = gdb.request("qXfer:features:read:target.xml:0,10");
+    assert_eq!(first, "m<?xml version=\"1");
+
+    let mut xml = String::new();
+    loop {
+        let reply = gdb.request(&format!(
+            "qXfer:features:read:target.xml:{:x},100",
+            xml.len()
+        ));
+        xml.push_str(&reply[1..]);
+        if reply.starts_with('l') {
+            break;
+        }
+    }
+    assert!(xml.starts_with("<?xml"));
+    assert!(xml.contains(r#"<reg name="pc" bitsize="16" type="code_ptr"/>"#));
+    assert!(xml.trim_end().ends_with("</target>"));
+
+    assert_eq!(gdb.request("qXfer:features:read:target.xml:zz,10"), "E01");
+}
+
+#[test]
+fn faults_use_up_their_place_in_the_frame() {
+    let program = [
+        0x60, 0x05, // V0 = 5
+        0xF0, 0x15, // DT = V0
+        0xAF, 0xFF, // I = 0xFFF
+        0xF1, 0x65, // Load V0 and V1 from I, which faults
+        0x71, 0x01, // 0x208: V1 += 1
+        0xF2, 0x07, // V2 = DT
+        0x42, 0x05, // Loop while DT is still 5
+        0x12, 0x08, //
+        0x12, 0x10, // 0x210: done
+    ];
+    let mut gdb = Gdb::start_with(
+        "fault",
+        &program,
+        &["--tickrate", "14", "--frames", "2", "--platform", "chip8"],
+    );
+    assert_eq!(gdb.request("Z0,210,2"), "OK");
+    assert_eq!(gdb.request("c"), "S0b");
+
+    // The debugger runs the first instruction itself, then the frame has 11 left, so 3 whole
+    // loops run before the timer ticks and the 4th sees it
+    assert_eq!(gdb.request("c208"), "S05");
+    assert_eq!(gdb.request("p11"), "0210");
+    assert_eq!(gdb.request("p1"), "04");
+}
@@ -0,0 +1,150 @@
+use chip8::{
+    asm, disasm,
+    instructions::{Instruction, Syntax},
+    platform::Platform,
+    registers::Register::{V0, V3, V5, VA, VE, VF},
+};
+
+/// One of every instruction, with operands that use all of their bits somewhere
+const SAMPLES: &[Instruction] = &[
+    Instruction::ClearDisplay,
+    Instruction::ReturnSubroutine,
+    Instruction::Jump { addr: 0x200 },
+    Instruction::CallSubroutine { addr: 0xFFF },
+    Instruction::SkipEq { reg: VA, num: 0xFF },
+    Instruction::SkipNe { reg: V3, num: 0x5A },
+    Instruction::SkipEqReg { reg1: V0, reg2: VF },
+    Instruction::SkipNeReg { reg1: VF, reg2: V0 },
+    Instruction::Set { reg: VE, val: 0x80 },
+    Instruction::Add { reg: V5, val: 0x01 },
+    Instruction::SetReg { reg1: V3, reg2: V5 },
+    Instruction::Or { reg1: V3, reg2: V5 },
+    Instruction::And { reg1: V3, reg2: V5 },
+    Instruction::Xor { reg1: V3, reg2: V5 },
+    Instruction::AddReg { reg1: V3, reg2: V5 },
+    Instruction::Sub1 { reg1: V3, reg2: V5 },
+    Instruction::Sub2 { reg1: V3, reg2: V5 },
+    Instruction::Shr { reg1: V3, reg2: V5 },
+    Instruction::Shl { reg1: V3, reg2: V5 },
+    Instruction::SetIndex { val: 0xABC },
+    Instruction::JumpOffset { addr: 0x300 },
+    Instruction::Rand {
+        outreg: VA,
+        val: 0x0F,
+    },
+    Instruction::Display {
+        xreg: V0,
+        yreg: VE,
+        height: 0xF,
+    },
+    Instruction::Display {
+        xreg: V5,
+        yreg: V3,
+        height: 0,
+    },
+    Instruction::SkipIfKey { keyreg: VE },
+    Instruction::SkipIfNotKey { keyreg: V3 },
+    Instruction::GetDelayTimer { outreg: VF },
+    Instruction::WaitForKey { keyreg: V5 },
+    Instruction::SetDelayTimer { inreg: VA },
+    Instruction::SetSoundTimer { inreg: VA },
+    Instruction::AddToIndex { inreg: V0 },
+    Instruction::GetFontChar { inreg: V3 },
+    Instruction::GetBigFontChar { inreg: V3 },
+    Instruction::BinToDec { inreg: VE },
+    Instruction::StoreMem { inreg_max: VF },
+    Instruction::LoadMem { outreg_max: V5 },
+    Instruction::ScrollDown { amount: 0xF },
+    Instruction::ScrollUp { amount: 3 },
+    Instruction::ScrollRight,
+    Instruction::ScrollLeft,
+    Instruction::Exit,
+    Instruction::LowRes,
+    Instruction::HighRes,
+    Instruction::StoreFlags { inreg_max: V5 },
+    Instruction::LoadFlags { outreg_max: VF },
+    Instruction::StoreRange { reg1: V3, reg2: VA },
+    Instruction::LoadRange { reg1: VA, reg2: V3 },
+    Instruction::LongSetIndex { val: 0xFEDC },
+    Instruction::SelectPlanes { planes: 3 },
+    Instruction::LoadAudioPattern,
+    Instruction::SetPitch { inreg: VE },
+];
+
+fn parse(bytes: &[u8]) -> Option<Instruction> {
+    let word = |at: usize| {
+        bytes
+            .get(at..at + 2)
+            .map_or(0, |pair| u16::from_be_bytes([pair[0], pair[1]]))
+    };
+    Instruction::parse_long(word(0), word(2))
+}
+
+#[test]
+fn every_instruction_encodes_and_parses_back() {
+    for &instruction in SAMPLES {
+        let bytes = instruction.encode();
+        assert_eq!(
+            bytes.len(),
+            usize::from(instruction.size()),
+            "{instruction:?}"
+        );
+        assert_eq!(parse(&bytes), Some(instruction), "{bytes:02X?}");
+    }
+}
+
+#[test]
+fn every_opcode_parses_and_encodes_back() {
+    for opcode in 0..=u16::MAX {
+        let nexts: &[u16] = if Instruction::is_long(opcode) {
+            &[0x0000, 0x1234, 0xFFFF]
+        } else {
+            &[0x0000]
+        };
+        for &next in nexts {
+            let Some(instruction) = Instruction::parse_long(opcode, next) else {
+                continue;
+            };
+            let mut bytes = opcode.to_be_bytes().to_vec();
+            if instruction.size() == 4 {
+                bytes.extend(next.to_be_bytes());
+            }
+            assert_eq!(instruction.encode(), bytes, "{instruction:?}");
+        }
+    }
+}
+
+#[test]
+fn disassembly_assembles_to_the_same_bytes() {
+    let mut prg: Vec<u8> = SAMPLES
+        .iter()
+        .flat_map(|instruction| instruction.encode())
+        .collect();
+    // Data which doesn't decode, and a trailing odd byte
+    prg.extend([0xFF, 0xFF, 0x5A]);
+
+    let listing = disasm::disassemble(&prg, Platform::XoChip).listing(Syntax::Cowgod);
+    let assembled = asm::assemble(&listing).unwrap_or_else(|err| panic!("{err}\n{listing}"));
+    assert_eq!(assembled, prg, "{listing}");
+}
+
+#[test]
+fn expressions_can_refer_to_later_labels() {
+    let source = "start:\n DB end - start\n LD I, data - 0x100\n CLS\nend:\ndata: DB 1\n";
+    assert_eq!(
+        asm::assemble(source).unwrap_or_else(|err| panic!("{err}")),
+        [0x05, 0xA1, 0x05, 0x00, 0xE0, 0x01]
+    );
+
+    // Values are still checked once the labels are known
+    let err = asm::assemble(" DB end\nend:\n").unwrap_err();
+    assert_eq!(err.line, 1);
+    assert_eq!(err.message, "end = 513 doesn't fit in a byte");
+}
+
+#[test]
+fn assembler_rejects_overflowing_values() {
+    let err = asm::assemble("X EQU 0x7FFFFFFFFFFFFFFF\nLD V0, X + X\n").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert_eq!(err.message, "value out of range");
+}
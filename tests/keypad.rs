@@ -0,0 +1,66 @@
+use chip8::{platform::Platform, quirks::Quirks, registers::Register, Machine};
+
+/// 0x200: wait for a key into V0, 0x202: loop forever
+const PROGRAM: &[u8] = &[0xF0, 0x0A, 0x12, 0x02];
+
+const NONE: [bool; 16] = [false; 16];
+
+fn keys(held: &[usize]) -> [bool; 16] {
+    std::array::from_fn(|key| held.contains(&key))
+}
+
+/// Runs a frame with the given keys held, returning whether FX0A is done waiting
+fn frame(machine: &mut Machine, keys: [bool; 16]) -> bool {
+    machine.set_keys(keys);
+    machine.run_frame(10).unwrap();
+    machine.chip8().pc != 0x200
+}
+
+#[test]
+fn waits_for_a_press_and_release() {
+    let mut machine = Machine::new(PROGRAM, Platform::Chip8).unwrap();
+    assert!(!frame(&mut machine, NONE));
+    // Holding the key across frames doesn't end the wait
+    assert!(!frame(&mut machine, keys(&[5])));
+    assert!(!frame(&mut machine, keys(&[5])));
+    assert!(frame(&mut machine, NONE));
+    assert_eq!(machine.chip8().registers[Register::V0], 5);
+}
+
+#[test]
+fn keys_held_before_waiting_are_ignored() {
+    let mut machine = Machine::new(PROGRAM, Platform::Chip8).unwrap();
+    assert!(!frame(&mut machine, keys(&[5])));
+    assert!(!frame(&mut machine, NONE));
+
+    // Pressing it again counts, as does releasing another key first
+    assert!(!frame(&mut machine, keys(&[5, 0xA])));
+    assert!(frame(&mut machine, keys(&[5])));
+    assert_eq!(machine.chip8().registers[Register::V0], 0xA);
+}
+
+#[test]
+fn waits_survive_save_states() {
+    let mut machine = Machine::new(PROGRAM, Platform::Chip8).unwrap();
+    frame(&mut machine, NONE);
+    frame(&mut machine, keys(&[7]));
+    let state = machine.save_state();
+
+    let mut restored = Machine::new(PROGRAM, Platform::Chip8).unwrap();
+    restored.load_state(&state).unwrap();
+    assert!(!frame(&mut restored, keys(&[7])));
+    assert!(frame(&mut restored, NONE));
+    assert_eq!(restored.chip8().registers[Register::V0], 7);
+}
+
+#[test]
+fn wait_key_press_ends_on_the_press() {
+    let mut machine = Machine::new(PROGRAM, Platform::Chip8).unwrap();
+    machine.set_quirks(Quirks {
+        wait_key_press: true,
+        ..Quirks::CHIP8
+    });
+    assert!(!frame(&mut machine, NONE));
+    assert!(frame(&mut machine, keys(&[3, 9])));
+    assert_eq!(machine.chip8().registers[Register::V0], 3);
+}
@@ -0,0 +1,59 @@
+use chip8::{
+    movie::{Frame, Movie},
+    platform::Platform,
+    Machine,
+};
+
+fn keys(held: &[usize]) -> [bool; 16] {
+    std::array::from_fn(|key| held.contains(&key))
+}
+
+#[test]
+fn frames_round_trip_with_key_0_in_the_lowest_bit() {
+    let machine = Machine::new(&[0x12, 0x00], Platform::Chip8).unwrap();
+    let mut movie = Movie::record(&machine);
+    let frames = [
+        Frame {
+            keys: keys(&[]),
+            instructions: 10,
+        },
+        Frame {
+            keys: keys(&[0]),
+            instructions: 11,
+        },
+        Frame {
+            keys: keys(&[1, 0xF]),
+            instructions: 0x0102_0304,
+        },
+    ];
+    for frame in frames {
+        movie.push(frame);
+    }
+
+    let bytes = movie.to_bytes();
+    assert_eq!(
+        bytes[bytes.len() - 18..],
+        [
+            0x00, 0x00, 0, 0, 0, 10, // No keys
+            0x00, 0x01, 0, 0, 0, 11, // Key 0
+            0x80, 0x02, 1, 2, 3, 4, // Keys 1 and F
+        ]
+    );
+
+    let parsed = Movie::parse(&bytes).unwrap();
+    assert_eq!(parsed.frames(), frames);
+    assert_eq!(parsed.start_state(), machine.save_state());
+}
+
+#[test]
+fn truncated_movies_are_rejected() {
+    let machine = Machine::new(&[0x12, 0x00], Platform::Chip8).unwrap();
+    let mut movie = Movie::record(&machine);
+    movie.push(Frame {
+        keys: keys(&[3]),
+        instructions: 1,
+    });
+    let bytes = movie.to_bytes();
+    assert!(Movie::parse(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Movie::parse(&bytes[1..]).is_err());
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use chip8::octo;
+
+fn compile(source: &str) -> Vec<u8> {
+    octo::compile(source).unwrap_or_else(|err| panic!("{err}"))
+}
+
+#[test]
+fn starts_with_a_jump_to_main() {
+    assert_eq!(
+        compile(": main v0 := 5 v1 += 2"),
+        [0x12, 0x02, 0x60, 0x05, 0x71, 0x02]
+    );
+}
+
+#[test]
+fn aliases_name_registers() {
+    assert_eq!(
+        compile(":alias x v3 : main x := 7 v0 += x"),
+        [0x12, 0x02, 0x63, 0x07, 0x80, 0x34]
+    );
+}
+
+#[test]
+fn constants_stand_in_for_values() {
+    assert_eq!(
+        compile(":const N 9 : main v0 := N vA := -1"),
+        [0x12, 0x02, 0x60, 0x09, 0x6A, 0xFF]
+    );
+}
+
+#[test]
+fn macros_substitute_their_arguments() {
+    assert_eq!(
+        compile(":macro set2 a b { a := b } : main set2 v4 3 set2 v5 4"),
+        [0x12, 0x02, 0x64, 0x03, 0x65, 0x04]
+    );
+}
+
+#[test]
+fn calc_evaluates_right_to_left() {
+    assert_eq!(
+        compile(
+            ":calc A { 10 - 2 - 3 } :calc B { ( 10 - 2 ) - 3 } :calc C { A * 2 + B } \
+             : main v0 := A v1 := B v2 := C"
+        ),
+        [0x12, 0x02, 0x60, 11, 0x61, 5, 0x62, 77]
+    );
+}
+
+#[test]
+fn loops_jump_back_and_while_exits() {
+    assert_eq!(
+        compile(": main loop v0 += 1 while v0 != 5 again"),
+        [0x12, 0x02, 0x70, 0x01, 0x40, 0x05, 0x12, 0x0A, 0x12, 0x02]
+    );
+}
+
+#[test]
+fn if_then_skips_one_statement() {
+    assert_eq!(
+        compile(": main if v0 == 1 then v1 := 1"),
+        [0x12, 0x02, 0x40, 0x01, 0x61, 0x01]
+    );
+}
+
+#[test]
+fn if_begin_else_end_jumps_around_branches() {
+    assert_eq!(
+        compile(": main if v0 == 1 begin v1 := 1 else v1 := 2 end"),
+        [0x12, 0x02, 0x30, 0x01, 0x12, 0x0A, 0x61, 0x01, 0x12, 0x0C, 0x61, 0x02]
+    );
+}
+
+#[test]
+fn forward_labels_are_patched() {
+    assert_eq!(
+        compile(
+            ": main jump later : sub i := data i := long data return \
+             : later sub : data 1 2"
+        ),
+        [
+            0x12, 0x02, // jump main
+            0x12, 0x0C, // jump later
+            0xA2, 0x0E, // i := data
+            0xF0, 0x00, 0x02, 0x0E, // i := long data
+            0x00, 0xEE, // return
+            0x22, 0x04, // sub
+            0x01, 0x02, // data
+        ]
+    );
+}
+
+#[test]
+fn long_labels_can_be_past_4k() {
+    let rom = compile(": main i := long far :org 0x1200 : far 0xAA");
+    assert_eq!(rom[2..6], [0xF0, 0x00, 0x12, 0x00]);
+    assert_eq!(rom[0x1000], 0xAA);
+}
+
+#[test]
+fn undefined_names_report_file_line_and_column() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("undefined.8o");
+    std::fs::write(&path, ": main\n  v0 := 1\n  jump nowhere\n").unwrap();
+
+    let err = octo::compile_file(&path).unwrap_err();
+    assert_eq!((err.line, err.column), (3, 8));
+    assert_eq!(
+        err.to_string(),
+        format!("{}:3:8: undefined name 'nowhere'", path.display())
+    );
+}
+
+#[test]
+fn unclosed_blocks_are_errors() {
+    let err = octo::compile(": main\nloop\n  v0 += 1\n").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 1));
+    assert_eq!(err.message, "'loop' is never closed");
+}
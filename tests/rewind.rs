@@ -0,0 +1,122 @@
+use chip8::{platform::Platform, rewind::Rewind, Machine};
+
+/// Each state changes the last in a few places, with gaps and runs long enough to need
+/// multi-byte lengths, and one changes size
+fn states() -> Vec<Vec<u8>> {
+    let mut state: Vec<u8> = (0..=255).cycle().take(5000).collect();
+    let mut states = vec![state.clone()];
+
+    state[0] = 0xAA;
+    states.push(state.clone());
+
+    state[300..500].fill(0x11);
+    state[4999] ^= 0xFF;
+    states.push(state.clone());
+
+    // Unchanged
+    states.push(state.clone());
+
+    state.truncate(4000);
+    states.push(state.clone());
+
+    state[130] = 0;
+    state[3000..3129].reverse();
+    states.push(state);
+    states
+}
+
+#[test]
+fn steps_back_through_every_state() {
+    let states = states();
+    let mut rewind = Rewind::new(100, usize::MAX);
+    assert!(rewind.is_empty());
+    for state in &states {
+        rewind.push(state.clone());
+    }
+    assert_eq!(rewind.len(), states.len());
+
+    for (idx, expected) in states.iter().enumerate().rev().skip(1) {
+        assert_eq!(rewind.step_back(), Some(expected.as_slice()), "state {idx}");
+        assert_eq!(rewind.len(), idx + 1);
+    }
+    assert_eq!(rewind.step_back(), None);
+    assert_eq!(rewind.len(), 1);
+    assert_eq!(rewind.size(), states[0].len());
+}
+
+#[test]
+fn only_the_newest_state_is_kept_in_full() {
+    let states = states();
+    let mut rewind = Rewind::new(100, usize::MAX);
+    for state in &states[..4] {
+        rewind.push(state.clone());
+    }
+    // The three deltas are small next to a second full copy
+    assert!(rewind.size() < states[0].len() + 500);
+}
+
+#[test]
+fn restores_machine_states() {
+    // Counts V0 up, drawing the font character for its low digit each time
+    let program = [
+        0x00, 0xE0, // CLS
+        0xF0, 0x29, // I = font character V0
+        0xD0, 0x05, // Draw it at V0, V0
+        0x70, 0x01, // V0 += 1
+        0x12, 0x00, // Loop
+    ];
+    let mut machine = Machine::new(&program, Platform::Chip8).unwrap();
+    let mut rewind = Rewind::new(100, usize::MAX);
+    let mut saved = Vec::new();
+    for _ in 0..20 {
+        machine.run_frame(7).unwrap();
+        let state = machine.save_state();
+        saved.push(state.clone());
+        rewind.push(state);
+    }
+
+    for expected in saved.iter().rev().skip(1) {
+        let state = rewind.step_back().unwrap().to_vec();
+        assert_eq!(&state, expected);
+        machine.load_state(&state).unwrap();
+        assert_eq!(machine.save_state(), state);
+    }
+}
+
+#[test]
+fn drops_the_oldest_states_past_the_limit() {
+    let states = states();
+    let mut rewind = Rewind::new(3, usize::MAX);
+    for state in &states {
+        rewind.push(state.clone());
+    }
+    assert_eq!(rewind.len(), 3);
+    assert_eq!(rewind.step_back(), Some(states[4].as_slice()));
+    assert_eq!(rewind.step_back(), Some(states[3].as_slice()));
+    assert_eq!(rewind.step_back(), None);
+}
+
+#[test]
+fn drops_the_oldest_states_past_the_memory_limit() {
+    let states = states();
+    // Room for the newest state and a small delta, but not the full state from before the
+    // resize
+    let max_bytes = states[5].len() + 800;
+    let mut rewind = Rewind::new(100, max_bytes);
+    for state in &states {
+        rewind.push(state.clone());
+    }
+    assert!(rewind.size() <= max_bytes);
+    assert_eq!(rewind.len(), 2);
+    assert_eq!(rewind.step_back(), Some(states[4].as_slice()));
+    assert_eq!(rewind.step_back(), None);
+}
+
+#[test]
+fn a_zero_limit_keeps_nothing() {
+    let mut rewind = Rewind::new(0, usize::MAX);
+    rewind.push(vec![1, 2, 3]);
+    assert!(rewind.is_empty());
+    assert_eq!(rewind.size(), 0);
+    assert_eq!(rewind.step_back(), None);
+}